@@ -0,0 +1,171 @@
+use crate::*;
+
+// Canonical forms of RecExprs.
+// Within a RecExpr, a private slot of a node binds this slot in all children of the node.
+
+impl<L: Language> RecExpr<L> {
+    // Renames all bound slots canonically, free slots keep their names.
+    // Two terms are alpha-equivalent iff their alpha-normalizations are equal.
+    pub fn alpha_normalize(&self) -> RecExpr<L> {
+        let re = annotate(self);
+        let free = re.node.slots();
+        let mut binders = (0..).map(Slot::new).filter(|s| !free.contains(s));
+        rename(&re, &SlotMap::new(), &mut |x| x, &mut binders)
+    }
+
+    // let re.canonical_form() = (sh, bij); then
+    // - the free slots of sh are s0, ..., s(n-1), ordered by their first occurrence. Bound slots are numbered after them.
+    // - sh.apply_slotmap(bij) is alpha-equivalent to re.
+    // - sh is the same for all terms that are equal up to alpha-equivalence and renaming of free slots.
+    pub fn canonical_form(&self) -> (RecExpr<L>, Bijection) {
+        let re = annotate(self);
+        let n = re.node.slots().len();
+        let mut binders = (n..).map(Slot::new);
+        let mut free = SlotMap::new();
+        let sh = rename(&re, &SlotMap::new(), &mut |x| {
+            if !free.contains_key(x) {
+                free.insert(x, Slot::new(free.len()));
+            }
+            free[x]
+        }, &mut binders);
+        (sh, free.inverse())
+    }
+
+    // The free slots, ordered by their first occurrence.
+    pub fn free_slots(&self) -> Vec<Slot> {
+        let (_, bij) = self.canonical_form();
+        (0..bij.len()).map(|i| bij[Slot::new(i)]).collect()
+    }
+
+    // Renames the free slots. Bound slots that would collide with the new names are renamed as well.
+    pub fn apply_slotmap(&self, m: &SlotMap) -> RecExpr<L> {
+        let re = annotate(self);
+        if CHECKS {
+            assert!(m.keys().is_superset(&re.node.slots()), "RecExpr::apply_slotmap: The SlotMap doesn't map all free slots!");
+        }
+        let values = m.values();
+        let mut binders = (0..).map(Slot::new).filter(|s| !values.contains(s));
+        rename(&re, &SlotMap::new(), &mut |x| m[x], &mut binders)
+    }
+}
+
+// Sets each AppliedId of a node to the identity on the free slots of the corresponding child.
+// Hence `node.slots()` are the free slots of the whole term.
+fn annotate<L: Language>(re: &RecExpr<L>) -> RecExpr<L> {
+    let children: Vec<RecExpr<L>> = re.children.iter().map(annotate).collect();
+    let mut node = re.node.clone();
+    for (x, c) in node.applied_id_occurences_mut().into_iter().zip(&children) {
+        *x = AppliedId::new(Id(0), SlotMap::identity(&c.node.slots()));
+    }
+    RecExpr { node, children }
+}
+
+// `re` has to be annotated.
+// `env` contains the new names of the slots bound further up, all other free slots are renamed by `free`.
+// Every binder gets a new name from `binders`, in the order in which they appear.
+fn rename<L: Language>(re: &RecExpr<L>, env: &SlotMap, free: &mut impl FnMut(Slot) -> Slot, binders: &mut impl Iterator<Item=Slot>) -> RecExpr<L> {
+    let mut node = nullify_app_ids(&re.node);
+
+    let mut bound = SlotMap::new();
+    for x in node.private_slot_occurences() {
+        if !bound.contains_key(x) {
+            bound.insert(x, binders.next().unwrap());
+        }
+    }
+
+    // slots that are bound by this node, but don't appear in the node itself.
+    let mut implicit: Vec<Slot> = re.node.private_slots().into_iter().filter(|x| !bound.contains_key(*x)).collect();
+    implicit.sort();
+    for x in implicit {
+        bound.insert(x, binders.next().unwrap());
+    }
+
+    for x in node.all_slot_occurences_mut() {
+        *x = match (bound.get(*x), env.get(*x)) {
+            (Some(y), _) | (None, Some(y)) => y,
+            (None, None) => free(*x),
+        };
+    }
+
+    let children = re.children.iter().map(|c| {
+        let env2: SlotMap = c.node.slots().into_iter()
+            .filter_map(|x| bound.get(x).or_else(|| env.get(x)).map(|y| (x, y)))
+            .collect();
+        rename(c, &env2, free, binders)
+    }).collect();
+
+    RecExpr { node, children }
+}
+
+// A RecExpr, that is compared and hashed up to alpha-equivalence.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct AlphaRecExpr<L: Language>(RecExpr<L>);
+
+impl<L: Language> AlphaRecExpr<L> {
+    pub fn new(re: &RecExpr<L>) -> Self {
+        AlphaRecExpr(re.alpha_normalize())
+    }
+
+    // the alpha-normalized term.
+    pub fn expr(&self) -> &RecExpr<L> {
+        &self.0
+    }
+}
+
+// A RecExpr, that is compared and hashed up to alpha-equivalence and renaming of free slots.
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ShapeRecExpr<L: Language>(RecExpr<L>);
+
+impl<L: Language> ShapeRecExpr<L> {
+    pub fn new(re: &RecExpr<L>) -> Self {
+        ShapeRecExpr(re.canonical_form().0)
+    }
+
+    // the canonical form of the term.
+    pub fn expr(&self) -> &RecExpr<L> {
+        &self.0
+    }
+}
+
+impl<L: Language> std::fmt::Display for AlphaRecExpr<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl<L: Language> std::fmt::Display for ShapeRecExpr<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[test]
+fn canonical_forms() {
+    let p = |s| RecExpr::<RiseENode>::parse(s).unwrap();
+
+    let a = p("(lam s3 (lam s4 (app (var s3) (app (var s0) (var s5)))))");
+    let b = p("(lam s7 (lam s1 (app (var s7) (app (var s0) (var s5)))))");
+    let c = p("(lam s7 (lam s1 (app (var s7) (app (var s5) (var s0)))))");
+    assert_eq!(a.alpha_normalize().to_string(), "(lam s1 (lam s2 (app (var s1) (app (var s0) (var s5)))))");
+    assert_eq!(a.free_slots(), vec![Slot::new(0), Slot::new(5)]);
+    assert_eq!(c.free_slots(), vec![Slot::new(5), Slot::new(0)]);
+
+    let (sh, bij) = c.canonical_form();
+    assert_eq!(sh.to_string(), "(lam s2 (lam s3 (app (var s2) (app (var s0) (var s1)))))");
+    assert_eq!(AlphaRecExpr::new(&sh.apply_slotmap(&bij)), AlphaRecExpr::new(&c));
+
+    let alpha: HashSet<_> = [&a, &b, &c].into_iter().map(AlphaRecExpr::new).collect();
+    let shape: HashSet<_> = [&a, &b, &c].into_iter().map(ShapeRecExpr::new).collect();
+    assert_eq!((alpha.len(), shape.len()), (2, 1));
+
+    // renaming free slots doesn't capture them.
+    let m = SlotMap::from_pairs(&[(Slot::new(0), Slot::new(1)), (Slot::new(5), Slot::new(2))]);
+    assert_eq!(a.apply_slotmap(&m).to_string(), "(lam s0 (lam s3 (app (var s0) (app (var s1) (var s2)))))");
+
+    // extracted terms use fresh names for their binders.
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(a.clone());
+    let out = ast_size_extract(i, &eg);
+    assert_ne!(out, a);
+    assert_eq!(AlphaRecExpr::new(&out), AlphaRecExpr::new(&a));
+}
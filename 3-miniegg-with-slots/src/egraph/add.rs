@@ -5,7 +5,7 @@ impl<L: Language> EGraph<L> {
     pub fn add_syn_expr(&mut self, re: RecExpr<L>) -> AppliedId {
         let mut n = re.node;
         let mut refs: Vec<&mut AppliedId> = n.applied_id_occurences_mut();
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(re.children.len(), refs.len());
         }
         for (i, child) in re.children.into_iter().enumerate() {
@@ -15,10 +15,14 @@ impl<L: Language> EGraph<L> {
     }
 
     pub fn add_syn(&mut self, enode: L) -> AppliedId {
+        self.with_slot_allocator(|eg| eg.add_syn_impl(enode))
+    }
+
+    fn add_syn_impl(&mut self, enode: L) -> AppliedId {
         self.add(enode.clone());
 
         if let Some(x) = self.lookup_syn(&enode) {
-            if CHECKS {
+            if self.check_level >= CheckLevel::Cheap {
                 assert_eq!(enode.slots(), x.slots());
             }
             return x;
@@ -33,7 +37,7 @@ impl<L: Language> EGraph<L> {
         let c_a = self.mk_syn_applied_id(c, fresh_to_old.clone());
         self.handle_congruence(c_a.id);
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(enode.slots(), c_a.slots());
         }
         c_a
@@ -51,11 +55,12 @@ impl<L: Language> EGraph<L> {
 }
 
 // semantic add:
+// These ignore the budget of the e-graph, see `try_add` and `try_add_expr` for the bounded versions.
 impl<L: Language> EGraph<L> {
     pub fn add_expr(&mut self, re: RecExpr<L>) -> AppliedId {
         let mut n = re.node;
         let mut refs: Vec<&mut AppliedId> = n.applied_id_occurences_mut();
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(re.children.len(), refs.len());
         }
         for (i, child) in re.children.into_iter().enumerate() {
@@ -66,7 +71,7 @@ impl<L: Language> EGraph<L> {
 
 
     pub fn add(&mut self, enode: L) -> AppliedId {
-        self.add_internal(self.shape(&enode))
+        self.with_slot_allocator(|eg| eg.add_internal(eg.shape(&enode)))
     }
 
     // self.add(x) = y implies that x.slots() is a superset of y.slots().
@@ -111,7 +116,7 @@ impl<L: Language> EGraph<L> {
             out,
         );
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(&c.slots, &app_id.m.keys());
         }
 
@@ -145,29 +150,29 @@ impl<L: Language> EGraph<L> {
 
     // adds (sh, bij) to the eclass `id`.
     pub(in crate::egraph) fn raw_add_to_class(&mut self, id: Id, (sh, bij): (L, Bijection), src_id: AppliedId) {
-        let tmp1 = self.classes.get_mut(&id).unwrap().nodes.insert(sh.clone(), (bij, src_id));
-        let tmp2 = self.hashcons.insert(sh.clone(), id);
-        if CHECKS {
+        self.touch(id);
+        let tmp1 = self.class_mut(id).nodes.insert(sh.clone(), (bij, src_id));
+        let tmp2 = self.hashcons_insert(sh.clone(), id);
+        if self.check_level >= CheckLevel::Cheap {
             assert!(tmp1.is_none());
             assert!(tmp2.is_none());
         }
         for ref_id in sh.ids() {
-            let usages = &mut self.classes.get_mut(&ref_id).unwrap().usages;
-            usages.insert(sh.clone());
+            self.class_mut(ref_id).usages.insert(sh.clone());
         }
     }
 
     // TODO: the bijection here is probably useless and error-prone. Let's only get Id and Shape as arguments here.
     pub(in crate::egraph) fn raw_remove_from_class(&mut self, id: Id, (sh, _bij): (L, Bijection)) {
-        let tmp1 = self.classes.get_mut(&id).unwrap().nodes.remove(&sh);
-        let tmp2 = self.hashcons.remove(&sh);
-        if CHECKS {
+        self.touch(id);
+        let tmp1 = self.class_mut(id).nodes.remove(&sh);
+        let tmp2 = self.hashcons_remove(&sh);
+        if self.check_level >= CheckLevel::Cheap {
             assert!(tmp1.is_some());
             assert!(tmp2.is_some());
         }
         for ref_id in sh.ids() {
-            let usages = &mut self.classes.get_mut(&ref_id).unwrap().usages;
-            usages.remove(&sh);
+            self.class_mut(ref_id).usages.remove(&sh);
         }
     }
 
@@ -197,17 +202,18 @@ impl<L: Language> EGraph<L> {
             redundancy_proof,
             syn_enode: syn_enode.clone(),
         };
-        self.classes.insert(c_id, c);
+        self.insert_class(c_id, c);
+        self.touch(c_id);
 
         { // add syn_enode to the hashcons.
             let (sh, bij) = syn_enode.weak_shape();
 
-            if CHECKS {
+            if self.check_level >= CheckLevel::Cheap {
                 assert!(!self.syn_hashcons.contains_key(&sh));
             }
 
             let app_id = self.mk_syn_applied_id(c_id, bij.inverse());
-            self.syn_hashcons.insert(sh, app_id);
+            self.syn_hashcons_insert(sh, app_id);
         }
 
         let app_id = self.mk_sem_identity_applied_id(c_id);
@@ -215,6 +221,8 @@ impl<L: Language> EGraph<L> {
         let prf = prove_reflexivity(&syn_app_id, &self.proof_registry);
         self.unionfind_set(c_id, syn_app_id, prf);
 
+        self.emit(|| Event::Created { id: c_id, slots: slots.clone() });
+
         c_id
     }
 }
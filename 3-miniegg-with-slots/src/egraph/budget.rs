@@ -0,0 +1,129 @@
+use crate::*;
+
+// Limits on the size of an e-graph. `None` means unlimited.
+// Once a limit is reached, rewrites stop adding e-nodes, see `budget_exhausted`.
+//
+// Only `try_add`, `try_add_expr` and `union_instantiations` respect the budget, and the built-in rewrites add their e-nodes through them.
+// `add`, `add_expr` and `union` ignore it, so that e.g. `prune` and `merge` can always copy an e-graph completely.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Budget {
+    pub max_nodes: Option<usize>,
+
+    // in bytes, as estimated by `memory_report`.
+    pub max_memory: Option<usize>,
+}
+
+impl<L: Language> EGraph<L> {
+    pub fn budget(&self) -> Budget {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+
+    // Whether the e-graph has reached one of its limits.
+    // Then `union_instantiations` refuses to add new e-nodes (returning UnionStatus::BudgetExhausted), `try_add` fails, and `do_rewrites` stops applying further rewrites.
+    // Unions of existing e-classes are still possible, as they don't grow the e-graph.
+    pub fn budget_exhausted(&self) -> bool {
+        let Budget { max_nodes, max_memory } = self.budget;
+        max_nodes.is_some_and(|n| self.total_number_of_nodes() >= n)
+            || max_memory.is_some_and(|m| self.estimated_memory() >= m)
+    }
+
+    // Like `add`, but returns None instead of adding a new e-node once the budget is exhausted.
+    // Appliers of rewrites should add their e-nodes this way, `add` itself ignores the budget.
+    pub fn try_add(&mut self, enode: L) -> Option<AppliedId> {
+        if let Some(x) = self.lookup(&enode) { return Some(x); }
+        if self.budget_exhausted() { return None; }
+        Some(self.add(enode))
+    }
+
+    // Like `add_expr`, but stops once the budget is exhausted. The sub-terms added until then stay in the e-graph.
+    pub fn try_add_expr(&mut self, re: RecExpr<L>) -> Option<AppliedId> {
+        let mut n = re.node;
+        let mut refs: Vec<&mut AppliedId> = n.applied_id_occurences_mut();
+        for (i, child) in re.children.into_iter().enumerate() {
+            *(refs[i]) = self.try_add_expr(child)?;
+        }
+        self.try_add(n)
+    }
+
+    // `memory_report` is linear in the size of the e-graph, so we only re-measure after the e-graph grew by 10%.
+    // In between, the memory is extrapolated from the number of e-nodes.
+    fn estimated_memory(&self) -> usize {
+        let n = self.total_number_of_nodes();
+        let (n0, bytes) = self.memory_estimate.get();
+        if n0 == 0 || n < n0 || n > n0 + n0 / 10 {
+            let bytes = self.memory_report().total();
+            self.memory_estimate.set((n, bytes));
+            return bytes;
+        }
+        bytes * n / n0
+    }
+}
+
+#[test]
+fn budget_stops_rewrites() {
+    let rules = rise_rules(SubstMethod::SmallStep);
+
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app (lam s0 (app (var s0) (var s0))) (lam s1 (var s1)))").unwrap());
+    let n = eg.total_number_of_nodes();
+    eg.set_budget(Budget { max_nodes: Some(n + 1), max_memory: None });
+
+    assert_eq!(do_rewrites(&mut eg, &rules), RewriteStatus::BudgetExhausted);
+    assert!(eg.budget_exhausted());
+    let m = eg.total_number_of_nodes();
+
+    // further iterations don't grow the e-graph.
+    assert_eq!(do_rewrites(&mut eg, &rules), RewriteStatus::BudgetExhausted);
+    assert_eq!(eg.total_number_of_nodes(), m);
+    assert_eq!(eg.try_add(RiseENode::Symbol(Symbol::new("budget_y"))), None);
+    assert_eq!(eg.try_add_expr(RecExpr::parse("(app sym_budget_f sym_budget_y)").unwrap()), None);
+
+    // new e-nodes are refused explicitly, unions of existing classes still work.
+    let subst = [(String::from("a"), i.clone())].into_iter().collect();
+    let pat = Pattern::parse("(app ?a ?a)").unwrap();
+    assert_eq!(eg.union_instantiations(&Pattern::parse("?a").unwrap(), &pat, &subst, None), UnionStatus::BudgetExhausted);
+    eg.check();
+
+    // a memory limit works the same way.
+    // eta-expansion grows the e-graph in every iteration.
+    let mut eg = EGraph::<RiseENode>::new();
+    eg.add_expr(RecExpr::parse("(lam s0 (app (var s0) sym_x))").unwrap());
+    let bytes = eg.memory_report().total();
+    eg.set_budget(Budget { max_nodes: None, max_memory: Some(2 * bytes) });
+    for _ in 0..10 {
+        do_rewrites(&mut eg, &rules);
+    }
+    assert!(eg.budget_exhausted());
+}
+
+#[test]
+fn budget_bounds_builtin_appliers() {
+    let p = |s| RecExpr::parse(s).unwrap();
+
+    // each built-in applier adds its e-nodes one at a time, and stops at the first one that is refused.
+    for m in [SubstMethod::Extraction, SubstMethod::SmallStep] {
+        let rules = rise_rules(m);
+        let mut eg = EGraph::<RiseENode>::new();
+        eg.add_expr(p("(app (lam s0 (app (var s0) (var s0))) (lam s1 (var s1)))"));
+        let n = eg.total_number_of_nodes();
+        eg.set_budget(Budget { max_nodes: Some(n + 1), max_memory: None });
+        for _ in 0..5 {
+            do_rewrites(&mut eg, &rules);
+        }
+        assert_eq!(eg.total_number_of_nodes(), n + 1);
+    }
+
+    let mut eg = EGraph::<ENode>::new();
+    eg.add_expr(RecExpr::<ENode>::parse2("(app (lam x (app x x)) (lam y y))"));
+    let n = eg.total_number_of_nodes();
+    eg.set_budget(Budget { max_nodes: Some(n + 1), max_memory: None });
+    for _ in 0..5 {
+        rewrite_small_step(&mut eg);
+        rewrite_big_step(&mut eg);
+    }
+    assert_eq!(eg.total_number_of_nodes(), n + 1);
+}
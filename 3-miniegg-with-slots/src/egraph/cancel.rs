@@ -0,0 +1,88 @@
+use crate::*;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+// A flag to interrupt long-running operations of an e-graph, possibly from another thread.
+#[derive(Clone, Debug, Default)]
+pub struct CancelToken(Arc<AtomicBool>);
+
+impl CancelToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+// Cancellation is cooperative: e-matching, rebuilding and extraction check `is_cancelled` regularly and stop early.
+// - `ematch_all` returns the matches found so far.
+// - `rebuild` leaves the remaining e-nodes pending; the e-graph stays consistent, but isn't congruence-closed until the next complete `rebuild`.
+// - unions don't change anything and return UnionStatus::Cancelled, and `do_rewrites` returns RewriteStatus::Cancelled.
+// - `Extractor::extract` returns None for the classes it didn't reach, see `Extractor::is_cancelled`.
+impl<L: Language> EGraph<L> {
+    // A handle to cancel the operations of this e-graph, which can be sent to other threads.
+    pub fn cancel_token(&self) -> CancelToken {
+        self.cancel_token.clone()
+    }
+
+    pub fn set_deadline(&mut self, deadline: Option<Instant>) {
+        self.deadline = deadline;
+    }
+
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        self.deadline = Some(Instant::now() + timeout);
+    }
+
+    // After a cancelled `rebuild`, some e-nodes are still pending, so the e-graph isn't congruence-closed.
+    // It's closed again by the next `rebuild` that runs to completion, e.g. after `reset_cancellation`.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel_token.is_cancelled() || self.deadline.is_some_and(|d| Instant::now() >= d)
+    }
+
+    // Removes the deadline, and replaces the cancel token by a fresh one.
+    pub fn reset_cancellation(&mut self) {
+        self.cancel_token = CancelToken::new();
+        self.deadline = None;
+    }
+}
+
+#[test]
+fn cancel_and_resume() {
+    let rules = rise_rules(SubstMethod::SmallStep);
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app (lam s0 (app (var s0) (var s0))) (lam s1 (var s1)))").unwrap());
+    let x = eg.add_expr(RecExpr::parse("sym_x").unwrap());
+    let n = eg.total_number_of_nodes();
+
+    let token = eg.cancel_token();
+    std::thread::spawn(move || token.cancel()).join().unwrap();
+    assert!(eg.is_cancelled());
+
+    assert_eq!(do_rewrites(&mut eg, &rules), RewriteStatus::Cancelled);
+    assert_eq!(eg.total_number_of_nodes(), n);
+    assert!(ematch_all(&eg, &Pattern::parse("(app ?a ?b)").unwrap()).is_empty());
+    assert_eq!(eg.union(&i, &x), UnionStatus::Cancelled);
+    let ex = Extractor::<_, AstSize>::new(&eg);
+    assert!(ex.is_cancelled());
+    assert_eq!(ex.extract(i.clone(), &eg), None);
+    eg.check();
+
+    // an expired deadline behaves the same way.
+    eg.reset_cancellation();
+    eg.set_deadline(Some(std::time::Instant::now()));
+    assert_eq!(do_rewrites(&mut eg, &rules), RewriteStatus::Cancelled);
+
+    eg.reset_cancellation();
+    assert!(!eg.is_cancelled());
+    assert_eq!(do_rewrites(&mut eg, &rules), RewriteStatus::Completed);
+    let ex = Extractor::<_, AstSize>::new(&eg);
+    assert!(!ex.is_cancelled());
+    assert!(ex.extract(i, &eg).is_some());
+}
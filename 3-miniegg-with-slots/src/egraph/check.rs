@@ -6,7 +6,7 @@ impl<L: Language> EGraph<L> {
     pub fn mk_sem_applied_id(&self, i: Id, m: SlotMap) -> AppliedId {
         let app_id = AppliedId::new(i, m);
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             self.check_sem_applied_id(&app_id);
         }
 
@@ -20,8 +20,21 @@ impl<L: Language> EGraph<L> {
 
     #[track_caller]
     pub fn check_sem_applied_id(&self, app_id: &AppliedId) {
-        app_id.check();
-        assert_eq!(self.slots(app_id.id), app_id.m.keys(), "checking sem AppliedId failed: Wrong key-set, {app_id:?}");
+        if let Some(v) = self.sem_applied_id_violation(app_id) {
+            panic!("checking sem AppliedId failed: {v:?}");
+        }
+    }
+
+    // `app_id` has to refer to an e-class with a bijection on exactly its slots.
+    fn sem_applied_id_violation(&self, app_id: &AppliedId) -> Option<Violation<L>> {
+        let Some(c) = self.classes.get(&app_id.id) else { return Some(Violation::MissingClass { id: app_id.id }) };
+        if !app_id.m.is_bijection() {
+            return Some(Violation::NonBijective { app: app_id.clone() });
+        }
+        if app_id.m.keys() != c.slots {
+            return Some(Violation::WrongSlots { app: app_id.clone(), expected: c.slots.clone() });
+        }
+        None
     }
 
 
@@ -30,7 +43,7 @@ impl<L: Language> EGraph<L> {
     pub fn mk_syn_applied_id(&self, i: Id, m: SlotMap) -> AppliedId {
         let app_id = AppliedId::new(i, m);
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             self.check_syn_applied_id(&app_id);
         }
 
@@ -46,114 +59,363 @@ impl<L: Language> EGraph<L> {
 
     #[track_caller]
     pub fn check_syn_applied_id(&self, app_id: &AppliedId) {
-        app_id.check();
-        assert_eq!(self.syn_slots(app_id.id), app_id.m.keys(), "checking syn AppliedId failed: Wrong key-set, {app_id:?}");
+        if let Some(v) = self.syn_applied_id_violation(app_id) {
+            panic!("checking syn AppliedId failed: {v:?}");
+        }
+    }
+
+    // `app_id` has to refer to an e-class with a bijection on exactly the slots of its syn_enode.
+    fn syn_applied_id_violation(&self, app_id: &AppliedId) -> Option<Violation<L>> {
+        let Some(c) = self.classes.get(&app_id.id) else { return Some(Violation::MissingClass { id: app_id.id }) };
+        if !app_id.m.is_bijection() {
+            return Some(Violation::NonBijective { app: app_id.clone() });
+        }
+        let syn_slots = c.syn_enode.slots();
+        if app_id.m.keys() != syn_slots {
+            return Some(Violation::WrongSynSlots { app: app_id.clone(), expected: syn_slots });
+        }
+        None
     }
 
 
     pub fn check(&self) {
-        // Checks whether the hashcons / usages are correct.
-        // And also checks that each Shape comes up in at most one EClass!
-        let mut hashcons = HashMap::default();
-        let mut usages = HashMap::default();
+        let violations = self.violations();
+        assert!(violations.is_empty(), "EGraph::check failed:\n{violations:#?}");
+    }
 
-        for (i, _) in &self.classes {
-            usages.insert(*i, HashSet::default());
+    // Checks the invariants of the e-graph, and returns all violations instead of panicking at the first one.
+    // The checks that rely on `find` (like computing the shapes of e-nodes) are skipped, if the unionfind, the redundancy proofs or the groups are broken.
+    pub fn violations(&self) -> Vec<Violation<L>> {
+        let mut out = Vec::new();
+        // the raw unionfind, as `find` can't follow broken entries.
+        let uf: Vec<(AppliedId, ProvenEq)> = self.unionfind.lock().unwrap().clone();
+        let n = uf.len();
+        let known = |i: Id| i.0 < n && self.classes.contains_key(&i);
+        let alive = |i: Id| uf[i.0].0.id == i;
+
+        for i in (0..n).map(Id) {
+            if !known(i) {
+                out.push(Violation::MissingClass { id: i });
+            }
+        }
+        for i in self.classes.keys() {
+            if !known(*i) {
+                out.push(Violation::MissingClass { id: *i });
+            }
+        }
+        if !out.is_empty() { return out; }
+
+        // every entry points to an existing class, and proves the equation it stands for.
+        for (i, (a, prf)) in uf.iter().enumerate() {
+            let i = Id(i);
+            // a.m :: slots(a.id) -> syn_slots(i), where slots(a.id) might have shrunk since.
+            if !known(a.id) || !a.m.is_bijection() || !a.m.keys().is_superset(&self.classes[&a.id].slots) || !a.m.values().is_subset(&self.syn_slots(i)) {
+                out.push(Violation::InvalidUnionfindEntry { id: i, entry: a.clone() });
+                continue;
+            }
+            if prf.l.id != i || prf.r.id != a.id {
+                out.push(Violation::InvalidUnionfindProof { id: i });
+                continue;
+            }
+            out.extend(self.syn_applied_id_violation(&prf.l));
+            out.extend(self.syn_applied_id_violation(&prf.r));
+        }
+
+        // following the unionfind always ends in a leader.
+        if out.is_empty() {
+            for i in (0..n).map(Id) {
+                let mut j = i;
+                for _ in 0..n {
+                    if alive(j) { break; }
+                    j = uf[j.0].0.id;
+                }
+                if !alive(j) {
+                    out.push(Violation::InvalidUnionfindEntry { id: i, entry: uf[i.0].0.clone() });
+                }
+            }
         }
 
         // redundancy-check for leaders.
         // TODO add a similar check for followers, using unionfind_get.
         for (i, c) in &self.classes {
-            if !self.is_alive(*i) { continue; }
+            if !alive(*i) { continue; }
 
             let eq = c.redundancy_proof.equ();
+            if eq.l.id != *i || eq.r.id != *i {
+                out.push(Violation::InvalidRedundancyProof { id: *i });
+                continue;
+            }
+            let syn = [&eq.l, &eq.r].map(|x| self.syn_applied_id_violation(x));
+            if syn.iter().any(|v| v.is_some()) {
+                out.extend(syn.into_iter().flatten());
+                continue;
+            }
+
             // eq.l.m :: slots(i) -> X
             // eq.r.m :: slots(i) -> X
             let tmp = eq.l.m.compose_partial(&eq.r.m.inverse());
-            assert!(tmp.is_perm());
-            assert_eq!(c.slots, tmp.keys());
-            assert_eq!(c.slots, tmp.values());
+            if !tmp.is_perm() || c.slots != tmp.keys() || c.slots != tmp.values() {
+                out.push(Violation::InvalidRedundancyProof { id: *i });
+            }
+        }
+
+        // the group is generated by proven permutations of the slots.
+        for (i, c) in &self.classes {
+            for p in c.group.generators() {
+                if !p.0.is_perm() || p.0.keys() != c.slots {
+                    out.push(Violation::InvalidPerm { id: *i, perm: p.0.clone() });
+                } else if !p.is_valid() {
+                    out.push(Violation::InvalidPermProof { id: *i, perm: p.0.clone() });
+                }
+            }
+        }
+
+        let sound = out.is_empty();
+
+        // Checks whether the hashcons / usages are correct.
+        // And also checks that each Shape comes up in at most one EClass!
+        let mut hashcons: HashMap<L, Id> = HashMap::default();
+        let mut usages: HashMap<Id, HashSet<L>> = HashMap::default();
+
+        for i in self.classes.keys() {
+            usages.insert(*i, HashSet::default());
         }
 
         for (i, c) in &self.classes {
             for sh in c.nodes.keys() {
-                assert!(!hashcons.contains_key(sh));
-                hashcons.insert(sh.clone(), *i);
+                if let Some(j) = hashcons.insert(sh.clone(), *i) {
+                    out.push(Violation::DuplicateShape { shape: sh.clone(), ids: (j, *i) });
+                }
 
                 for ref_id in sh.ids() {
-                    usages.get_mut(&ref_id).unwrap()
-                          .insert(sh.clone());
+                    match usages.get_mut(&ref_id) {
+                        Some(u) => { u.insert(sh.clone()); },
+                        None => out.push(Violation::MissingClass { id: ref_id }),
+                    }
                 }
             }
         }
 
-        assert_eq!(hashcons, self.hashcons);
-        for (i, c) in &self.classes {
-            assert_eq!(usages[&i], c.usages);
+        let extra = self.hashcons.keys().filter(|sh| !hashcons.contains_key(*sh));
+        for sh in hashcons.keys().chain(extra) {
+            let expected = hashcons.get(sh).copied();
+            let found = self.hashcons.get(sh).copied();
+            if expected != found {
+                out.push(Violation::HashconsMismatch { shape: sh.clone(), expected, found });
+            }
         }
 
         for (i, c) in &self.classes {
-            for p in c.group.all_perms() {
-                p.check();
+            if usages[i] != c.usages {
+                out.push(Violation::UsagesMismatch { id: *i, expected: usages[i].clone(), found: c.usages.clone() });
             }
         }
 
         // check that self.classes contains exactly these classes which point to themselves in the unionfind.
-        let all_keys = self.unionfind_iter().map(|(x, _)| x).collect::<HashSet<_>>();
-        let all_values = self.unionfind_iter().map(|(_, x)| x.id).collect::<HashSet<_>>();
-        let all_classes = self.classes.keys().copied().collect::<HashSet<_>>();
-        let all: HashSet<Id> = &(&all_keys | &all_values) | &all_classes;
-        for i in all {
+        let mut ids: Vec<Id> = self.classes.keys().copied().collect();
+        ids.sort();
+        for i in ids {
             // if they point to themselves, they should do it using the identity.
-            if self.is_alive(i) {
-                assert_eq!(self.unionfind_get(i), self.mk_sem_identity_applied_id(i));
+            if alive(i) {
+                if !sound { continue; }
+                let app = self.unionfind_get(i);
+                if app != AppliedId::new(i, SlotMap::identity(&self.slots(i))) {
+                    out.push(Violation::LeaderNotIdentity { id: i, found: app });
+                }
             } else {
-                assert!(self.classes[&i].nodes.is_empty());
+                if !self.classes[&i].nodes.is_empty() {
+                    out.push(Violation::DeadClassNotEmpty { id: i });
+                }
                 for sh in &self.classes[&i].usages {
-                    assert!(self.pending.contains(&sh));
+                    if !self.pending.contains(sh) {
+                        out.push(Violation::DeadUsageNotPending { id: i, shape: sh.clone() });
+                    }
                 }
             }
         }
 
         // check that no EClass has Slot(0) in its API.
-        for (_, c) in &self.classes {
-            assert!(!c.slots.contains(&Slot::new(0)));
+        for (i, c) in &self.classes {
+            if c.slots.contains(&Slot::new(0)) {
+                out.push(Violation::Slot0 { id: *i });
+            }
         }
 
         // Check that the Unionfind has valid AppliedIds.
-        for (_, app_id) in self.unionfind_iter() {
-            check_internal_applied_id::<L>(self, &app_id);
+        if sound {
+            for (_, app_id) in self.unionfind_iter() {
+                self.applied_id_violations(&app_id, &mut out);
+            }
         }
 
         // Check that all ENodes are valid.
-        for (_, c) in &self.classes {
+        for (i, c) in &self.classes {
             for (sh, (bij, _)) in &c.nodes {
                 let real = sh.apply_slotmap(bij);
-                assert!(real.slots().is_superset(&c.slots));
+                if !real.slots().is_superset(&c.slots) {
+                    out.push(Violation::MissingSlots { id: *i, enode: real.clone() });
+                }
+
+                if !sound || self.pending.contains(sh) { continue; }
 
-                if self.pending.contains(&sh) { continue; }
+                // the shape can only be computed for valid children.
+                let n = out.len();
+                for x in real.applied_id_occurences() {
+                    self.applied_id_violations(&x, &mut out);
+                }
+                if out.len() > n { continue; }
 
                 let (computed_sh, computed_bij) = self.shape(&real);
-                assert_eq!(&computed_sh, sh);
+                if &computed_sh != sh {
+                    out.push(Violation::ShapeMismatch { id: *i, shape: sh.clone(), computed: computed_sh });
+                    continue;
+                }
 
                 // computed_bij :: shape-slots -> slots(i)
                 // bij :: shape-slots -> slots(i)
-                let perm = computed_bij.inverse().compose_partial(&bij);
-                assert!(c.group.contains(&perm));
-
-                for x in real.applied_id_occurences() {
-                    check_internal_applied_id::<L>(self, &x);
+                let perm = computed_bij.inverse().compose_partial(bij);
+                if !c.group.contains(&perm) {
+                    out.push(Violation::GroupMismatch { id: *i, enode: real, perm });
                 }
             }
         }
 
-        fn check_internal_applied_id<L: Language>(eg: &EGraph<L>, app_id: &AppliedId) {
-            // 1. the app_id needs to be normalized!
-            let y = eg.find_applied_id(app_id);
-            assert_eq!(app_id, &y);
+        out
+    }
 
-            // 2. It needs to have exactly the same slots as the underlying EClass.
-            assert_eq!(&app_id.m.keys(), &eg.classes[&app_id.id].slots);
+    // requires a sound unionfind, see `violations`.
+    fn applied_id_violations(&self, app_id: &AppliedId, out: &mut Vec<Violation<L>>) {
+        if app_id.id.0 >= self.unionfind_len() || !self.classes.contains_key(&app_id.id) {
+            out.push(Violation::MissingClass { id: app_id.id });
+            return;
         }
+
+        // 1. It needs to have exactly the same slots as the underlying EClass.
+        if let Some(v) = self.sem_applied_id_violation(app_id) {
+            out.push(v);
+            return;
+        }
+
+        // 2. the app_id needs to be normalized!
+        let y = self.find_applied_id(app_id);
+        if app_id != &y {
+            out.push(Violation::NotNormalized { app: app_id.clone(), normalized: y });
+        }
+    }
+}
+
+// A violated invariant of the e-graph, as returned by `EGraph::violations`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation<L: Language> {
+    // An Id without e-class.
+    MissingClass { id: Id },
+
+    // The redundancy proof of the e-class doesn't fix its slots.
+    InvalidRedundancyProof { id: Id },
+
+    // The same shape is contained in two e-classes.
+    DuplicateShape { shape: L, ids: (Id, Id) },
+
+    // The hashcons doesn't agree with the e-nodes of the e-classes.
+    HashconsMismatch { shape: L, expected: Option<Id>, found: Option<Id> },
+
+    // The usages of an e-class don't agree with the e-nodes pointing to it.
+    UsagesMismatch { id: Id, expected: HashSet<L>, found: HashSet<L> },
+
+    // A generator of the group of an e-class which is no permutation of its slots.
+    InvalidPerm { id: Id, perm: Perm },
+
+    // A generator of the group of an e-class whose proof doesn't show that it is a symmetry.
+    InvalidPermProof { id: Id, perm: Perm },
+
+    // A unionfind entry that points to a missing class, doesn't fit the slots, or leads into a cycle.
+    InvalidUnionfindEntry { id: Id, entry: AppliedId },
+
+    // The proof of a unionfind entry is about different e-classes.
+    InvalidUnionfindProof { id: Id },
+
+    // A leader of the unionfind doesn't point to itself using the identity.
+    LeaderNotIdentity { id: Id, found: AppliedId },
+
+    // A dead e-class still contains e-nodes.
+    DeadClassNotEmpty { id: Id },
+
+    // A dead e-class is still used by an e-node, that isn't pending.
+    DeadUsageNotPending { id: Id, shape: L },
+
+    // Slot(0) is a slot of an e-class.
+    Slot0 { id: Id },
+
+    // An AppliedId whose SlotMap isn't a bijection.
+    NonBijective { app: AppliedId },
+
+    // An AppliedId that doesn't point to a leader.
+    NotNormalized { app: AppliedId, normalized: AppliedId },
+
+    // An AppliedId whose SlotMap doesn't have the slots of its e-class as keys.
+    WrongSlots { app: AppliedId, expected: HashSet<Slot> },
+
+    // An AppliedId in a proof, whose SlotMap doesn't have the slots of the syn_enode of its e-class as keys.
+    WrongSynSlots { app: AppliedId, expected: HashSet<Slot> },
+
+    // An e-node that doesn't contain all slots of its e-class.
+    MissingSlots { id: Id, enode: L },
+
+    // An e-node that is stored under the wrong shape.
+    ShapeMismatch { id: Id, shape: L, computed: L },
+
+    // An e-node whose slot renaming isn't explained by the group of its e-class.
+    GroupMismatch { id: Id, enode: L, perm: Perm },
+}
+
+#[test]
+fn violations_are_collected() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app (lam s0 (var s0)) sym_x)").unwrap());
+    assert!(eg.violations().is_empty());
+
+    let x = eg.add_expr(RecExpr::parse("sym_x").unwrap());
+    eg.classes.get_mut(&x.id).unwrap().usages.clear();
+    eg.hashcons.clear();
+
+    let v = eg.violations();
+    assert!(v.iter().any(|v| matches!(v, Violation::UsagesMismatch { id, .. } if *id == x.id)));
+    assert_eq!(v.iter().filter(|v| matches!(v, Violation::HashconsMismatch { found: None, .. })).count(), eg.classes.values().map(|c| c.nodes.len()).sum::<usize>());
+    assert!(!v.iter().any(|v| matches!(v, Violation::MissingClass { id } if *id == i.id)));
+}
+
+#[test]
+fn runtime_check_level() {
+    let mut eg = EGraph::<RiseENode>::new();
+    eg.set_check_level(CheckLevel::PerPending);
+    let a = eg.add_expr(RecExpr::parse("(app sym_f (app (var s1) (var s2)))").unwrap());
+    let b = eg.add_expr(RecExpr::parse("(app sym_f (app (var s2) (var s1)))").unwrap());
+    eg.union(&a, &b);
+
+    // a broken e-graph goes unnoticed without checks.
+    eg.set_check_level(CheckLevel::Off);
+    eg.hashcons.clear();
+    eg.rebuild();
+    assert!(!eg.violations().is_empty());
+
+    eg.set_check_level(CheckLevel::PerRebuild);
+    let res = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| eg.rebuild()));
+    assert!(res.is_err());
+}
+
+#[test]
+fn broken_unionfind_is_reported() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(lam s0 (var s0))").unwrap());
+
+    // let two leaders point to each other.
+    let x = eg.add_expr(RecExpr::parse("sym_x").unwrap());
+    {
+        let mut uf = eg.unionfind.lock().unwrap();
+        uf[i.id.0].0 = AppliedId::new(x.id, SlotMap::new());
+        uf[x.id.0].0 = AppliedId::new(i.id, SlotMap::new());
     }
+
+    let v = eg.violations();
+    assert!(v.iter().any(|v| matches!(v, Violation::InvalidUnionfindEntry { .. } | Violation::InvalidUnionfindProof { .. })));
 }
@@ -0,0 +1,150 @@
+use crate::*;
+
+// What changed in an e-graph since a Snapshot was taken.
+// The snapshot serves as the baseline: take it before e.g. a `do_rewrites` call, and compare afterwards using `EGraph::diff`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct EGraphDiff<L: Language> {
+    // Classes that didn't exist in the baseline and are still alive, with their slots.
+    pub new_classes: Vec<(Id, HashSet<Slot>)>,
+
+    // Classes that were alive in the baseline, but got merged into another class: id[identity] = AppliedId.
+    // The identity is on the slots that `id` had when it was merged, slots it lost before are redundant.
+    pub merged: Vec<(Id, AppliedId)>,
+
+    // E-nodes per (current) class, that don't correspond to any e-node of the baseline.
+    pub new_nodes: Vec<(Id, Vec<L>)>,
+
+    // Classes that are still alive, and lost these slots due to redundancy.
+    pub shrunk: Vec<(Id, HashSet<Slot>)>,
+}
+
+impl<L: Language> EGraphDiff<L> {
+    pub fn is_empty(&self) -> bool {
+        self.new_classes.is_empty() && self.merged.is_empty() && self.new_nodes.is_empty() && self.shrunk.is_empty()
+    }
+}
+
+impl<L: Language> EGraph<L> {
+    // Compares the e-graph against a snapshot that was taken from it earlier.
+    // All results are ordered by their ids.
+    pub fn diff(&self, base: &Snapshot<L>) -> EGraphDiff<L> {
+        let mut new_classes = Vec::new();
+        let mut shrunk = Vec::new();
+        for i in self.ids() {
+            if !base.is_alive(i) {
+                if i.0 >= base.number_of_ids() {
+                    new_classes.push((i, self.slots(i)));
+                }
+                continue;
+            }
+            let lost = base.slots(i) - &self.classes[&i].slots;
+            if !lost.is_empty() {
+                shrunk.push((i, lost));
+            }
+        }
+
+        // the slots that `i` lost before being merged are redundant, so the identity only covers the slots it still has.
+        let merged = (0..base.number_of_ids()).map(Id)
+            .filter(|i| base.is_alive(*i) && self.find_id(*i) != *i)
+            .map(|i| (i, self.find_applied_id(&self.mk_sem_identity_applied_id(i))))
+            .collect();
+
+        // the baseline e-nodes, canonicalized w.r.t. the current e-graph.
+        let old: HashSet<L> = base.shapes().map(|sh| self.shape(sh).0).collect();
+        let mut new_nodes = Vec::new();
+        for i in self.ids() {
+            let mut v: Vec<L> = self.classes[&i].nodes.iter()
+                .filter(|(sh, _)| !old.contains(sh))
+                .map(|(sh, (bij, _))| sh.apply_slotmap(bij))
+                .collect();
+            if v.is_empty() { continue; }
+            v.sort_by_key(|x| format!("{x:?}"));
+            new_nodes.push((i, v));
+        }
+
+        EGraphDiff { new_classes, merged, new_nodes, shrunk }
+    }
+}
+
+impl<L: Language> std::fmt::Display for EGraphDiff<L> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let slots_str = |s: &HashSet<Slot>| {
+            let mut v: Vec<Slot> = s.iter().copied().collect();
+            v.sort();
+            v.iter().map(|x| x.to_string()).collect::<Vec<_>>().join(", ")
+        };
+
+        for (i, slots) in &self.new_classes {
+            writeln!(f, "new class {i:?}({})", slots_str(slots))?;
+        }
+        for (i, a) in &self.merged {
+            writeln!(f, "merged {i:?} into {a:?}")?;
+        }
+        for (i, v) in &self.new_nodes {
+            for x in v {
+                writeln!(f, "new node in {i:?}: {x:?}")?;
+            }
+        }
+        for (i, slots) in &self.shrunk {
+            writeln!(f, "shrunk {i:?}: lost {}", slots_str(slots))?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn diff_after_rewrites() {
+    let p = |s| RecExpr::<RiseENode>::parse(s).unwrap();
+    let mut eg = EGraph::<RiseENode>::new();
+    let a = eg.add_expr(p("(app sym_f (var s0))"));
+    let b = eg.add_expr(p("(app sym_g (var s0))"));
+    let d = eg.add_expr(p("(app sym_h (var s2))"));
+
+    let base = eg.snapshot();
+    assert!(eg.diff(&base).is_empty());
+
+    eg.union(&a, &b);
+    let e = eg.add_expr(p("(app sym_k sym_k)"));
+    let d2 = eg.add_expr(p("(app sym_h (var s3))"));
+    eg.union(&d, &d2);
+
+    let diff = eg.diff(&base);
+    assert!(!diff.is_empty());
+    assert!(diff.new_classes.iter().any(|(i, _)| *i == eg.find_id(e.id)));
+    assert_eq!(diff.merged.len(), 1);
+    let (i, into) = &diff.merged[0];
+    assert!([a.id, b.id].contains(i));
+    assert_eq!(into.id, eg.find_id(a.id));
+    assert_eq!(into.slots().len(), 1);
+    assert_eq!(diff.shrunk.len(), 1);
+    assert_eq!((diff.shrunk[0].0, diff.shrunk[0].1.len()), (d.id, 1));
+
+    // the merged classes had their e-nodes before.
+    assert!(!diff.new_nodes.iter().any(|(i, _)| *i == eg.find_id(a.id)));
+    assert!(diff.new_nodes.iter().any(|(i, _)| *i == eg.find_id(e.id)));
+    assert!(diff.to_string().contains("merged"));
+}
+
+#[test]
+fn diff_merged_after_shrinking() {
+    let p = |s| RecExpr::<RiseENode>::parse(s).unwrap();
+    let mut eg = EGraph::<RiseENode>::new();
+    let a = eg.add_expr(p("(app (var s0) (var s1))"));
+    let b = eg.add_expr(p("(app sym_f (app (var s0) (var s1)))"));
+    let base = eg.snapshot();
+
+    // s1 becomes redundant in both classes, which get merged.
+    let b2 = eg.add_expr(p("(app sym_f (app (var s0) (var s2)))"));
+    eg.union(&a, &b2);
+
+    let diff = eg.diff(&base);
+    assert_eq!(diff.merged.len(), 1);
+    let (i, into) = &diff.merged[0];
+    assert!([a.id, b.id].contains(i));
+    assert_eq!(into.id, eg.find_id(a.id));
+
+    // `into` only refers to the slot that `i` still has.
+    assert_eq!(into.m.values(), eg.slots(*i));
+    assert_eq!(eg.slots(*i).len(), 1);
+    assert!(eg.eq(&eg.mk_sem_identity_applied_id(*i), into));
+}
@@ -0,0 +1,75 @@
+use crate::*;
+
+use std::fmt::Write;
+
+// Renders the e-graph in the Graphviz DOT format, eg. for `dot -Tsvg`.
+// - every e-class is a cluster, labelled with its slots and the generators of its symmetry group.
+// - every e-node is labelled with its operator, its slot arguments are shown as an external label.
+// - every edge goes from an e-node to the e-class of a child, and is labelled with the child's SlotMap.
+impl<L: Language> EGraph<L> {
+    pub fn to_dot(&self) -> String {
+        let nodes = self.sorted_enodes();
+
+        let mut out = String::from("digraph egraph {\n  compound=true;\n  clusterrank=local;\n");
+        for (i, xs) in &nodes {
+            let mut slots: Vec<Slot> = self.slots(*i).into_iter().collect();
+            slots.sort();
+            let slots: Vec<String> = slots.iter().map(|s| s.to_string()).collect();
+            let mut label = format!("{:?}({})", i, slots.join(", "));
+
+            let mut perms: Vec<Perm> = self.classes[i].group.generators().into_iter().map(|p| p.to_slotmap()).collect();
+            perms.sort();
+            for p in perms {
+                write!(label, "\n{p:?}").unwrap();
+            }
+
+            writeln!(out, "  subgraph cluster_{} {{", i.0).unwrap();
+            writeln!(out, "    style=dotted;").unwrap();
+            writeln!(out, "    label={};", quoted(&label)).unwrap();
+            for (k, x) in xs.iter().enumerate() {
+                let (op, slots) = op_and_slots(x);
+                let slots: Vec<String> = slots.iter().map(|s| s.to_string()).collect();
+                writeln!(out, "    \"{}.{k}\"[label={}, xlabel={}];", i.0, quoted(&op), quoted(&slots.join(" "))).unwrap();
+            }
+            writeln!(out, "  }}").unwrap();
+        }
+
+        for (i, xs) in &nodes {
+            for (k, x) in xs.iter().enumerate() {
+                for app in x.applied_id_occurences() {
+                    let c = self.find_id(app.id);
+                    // edges can only point to nodes, so we point to the first node of the class, and clip the edge at the cluster.
+                    writeln!(out, "  \"{}.{k}\" -> \"{}.0\"[lhead=cluster_{}, label={}];", i.0, c.0, c.0, quoted(&format!("{:?}", app.m))).unwrap();
+                }
+            }
+        }
+
+        out.push_str("}\n");
+        out
+    }
+}
+
+#[test]
+fn dot_export() {
+    let mut eg = EGraph::<RiseENode>::new();
+    eg.add_expr(RecExpr::parse("(lam s0 (app (var s0) sym_x))").unwrap());
+    let j = eg.add_expr(RecExpr::parse("(app (var s1) (var s2))").unwrap());
+    let k = eg.add_expr(RecExpr::parse("(app (var s2) (var s1))").unwrap());
+    eg.union(&j, &k);
+
+    let dot = eg.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert_eq!(dot.matches("subgraph cluster_").count(), eg.ids().len());
+    assert_eq!(dot.matches("[label=").count(), eg.total_number_of_nodes());
+    assert!(dot.contains("[label=\"lam\", xlabel=\"s"));
+
+    // the symmetry of (app (var s1) (var s2)) shows up in the label of its class.
+    let c = eg.find_id(j.id);
+    let label = dot.lines().skip_while(|l| *l != format!("  subgraph cluster_{} {{", c.0)).nth(2).unwrap();
+    assert!(label.contains("->"));
+
+    let prf = eg.explain_equivalence(RecExpr::parse("(app (var s1) (var s2))").unwrap(), RecExpr::parse("(app (var s2) (var s1))").unwrap());
+    let dot = prf.to_dot();
+    assert!(dot.starts_with("digraph"));
+    assert!(dot.contains(" = "));
+}
@@ -42,7 +42,7 @@ impl<L: Language> EGraph<L> {
     // now we want a proof that even makes the redundant slots work out.
     // We assume that `peq` is already maximally dis-associated. Hence we only need to re-associate some redundant slots to reach the goal.
     fn associate_necessaries(&self, goal: &Equation, peq: ProvenEq) -> ProvenEq {
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_match_equation(&self.semify_equation(goal), &self.semify_equation(&peq));
         }
         let l_red = self.get_redundancy_proof(peq.l.id);
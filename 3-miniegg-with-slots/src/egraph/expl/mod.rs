@@ -22,7 +22,7 @@ impl<L: Language> EGraph<L> {
         let (l1, prf1) = self.proven_find_applied_id(&i1);
         let (l2, prf2) = self.proven_find_applied_id(&i2);
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(l1.id, l2.id);
         }
         let id = l1.id;
@@ -36,7 +36,7 @@ impl<L: Language> EGraph<L> {
         let final_eq = Equation { l: i1, r: i2 };
         let p = TransitivityProof(prf1, prf2).check(&final_eq, &self.proof_registry);
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_proves_equation(&p, &final_eq);
         }
 
@@ -69,7 +69,64 @@ impl ProvenEqRaw {
         }
     }
 
-    fn subproofs(&self) -> Vec<&ProvenEq> {
+    // Renders the proof DAG in the Graphviz DOT format.
+    // Every lemma is a node, with edges to the lemmas it's proven by.
+    pub fn to_dot(&self) -> String {
+        self.to_dot_impl(&|i| format!("{i:?}"))
+    }
+
+    pub fn to_dot_expr<L: Language>(&self, eg: &EGraph<L>) -> String {
+        self.to_dot_impl(&|i| {
+            eg.get_syn_expr(i).to_string()
+        })
+    }
+
+    fn to_dot_impl(&self, f: &impl Fn(&AppliedId) -> String) -> String {
+        let mut names: HashMap<*const ProvenEqRaw, usize> = HashMap::default();
+        let mut nodes = String::new();
+        let mut edges = String::new();
+
+        let mut stack: Vec<&ProvenEqRaw> = vec![self];
+        while let Some(x) = stack.pop() {
+            let ptr = x as *const ProvenEqRaw;
+            if names.contains_key(&ptr) { continue; }
+            let i = names.len();
+            names.insert(ptr, i);
+
+            let rule = match x.proof() {
+                Proof::Explicit(ExplicitProof(Some(j))) => j.clone(),
+                Proof::Explicit(ExplicitProof(None)) => String::from("explicit"),
+                Proof::Reflexivity(_) => String::from("refl"),
+                Proof::Symmetry(_) => String::from("symmetry"),
+                Proof::Transitivity(_) => String::from("transitivity"),
+                Proof::Congruence(_) => String::from("congruence"),
+            };
+            let Equation { l, r } = &**x;
+            let label = format!("{} = {}\nby {rule}", f(l), f(r));
+            nodes.push_str(&format!("  p{i}[shape=box, label={}];\n", quoted(&label)));
+
+            for sub in x.subproofs() {
+                stack.push(sub);
+            }
+        }
+
+        // edges are only added once every lemma has its name.
+        let mut stack: Vec<&ProvenEqRaw> = vec![self];
+        let mut visited: HashSet<*const ProvenEqRaw> = HashSet::default();
+        while let Some(x) = stack.pop() {
+            let ptr = x as *const ProvenEqRaw;
+            if !visited.insert(ptr) { continue; }
+            for sub in x.subproofs() {
+                let subptr = (&**sub) as *const ProvenEqRaw;
+                edges.push_str(&format!("  p{} -> p{};\n", names[&ptr], names[&subptr]));
+                stack.push(sub);
+            }
+        }
+
+        format!("digraph proof {{\n{nodes}{edges}}}\n")
+    }
+
+    pub(crate) fn subproofs(&self) -> Vec<&ProvenEq> {
         match self.proof() {
             Proof::Explicit(ExplicitProof(j)) => vec![],
             Proof::Reflexivity(ReflexivityProof) => vec![],
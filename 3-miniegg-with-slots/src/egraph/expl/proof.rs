@@ -61,6 +61,11 @@ impl ProvenEqRaw {
         })
     }
 
+    // restores a stored ProvenEq without checking it, see EGraph::load.
+    pub(crate) fn new_unchecked(eq: Equation, proof: Proof) -> ProvenEq {
+        Arc::new(ProvenEqRaw { eq, proof })
+    }
+
     pub fn equ(&self) -> Equation {
         (**self).clone()
     }
@@ -308,6 +313,28 @@ pub fn assert_match_equation(a: &Equation, b: &Equation) -> SlotMap {
     theta
 }
 
+// Like `match_app_id`, but returns None instead of panicking.
+pub fn try_match_app_id(a: &AppliedId, b: &AppliedId) -> Option<SlotMap> {
+    if a.id != b.id || a.m.keys() != b.m.keys() || !a.m.is_bijection() || !b.m.is_bijection() { return None; }
+    let theta = a.m.inverse().compose(&b.m);
+    (&a.apply_slotmap(&theta) == b).then_some(theta)
+}
+
+// Like `assert_match_equation`, but returns None instead of panicking.
+pub fn try_match_equation(a: &Equation, b: &Equation) -> Option<SlotMap> {
+    let theta = try_match_app_id(&a.l, &b.l)?.try_union(&try_match_app_id(&a.r, &b.r)?)?;
+    if !theta.is_bijection() { return None; }
+    (&a.apply_slotmap(&theta) == b).then_some(theta)
+}
+
+// Like `assert_proves_equation`, but returns false instead of panicking.
+pub fn proves_equation(peq: &ProvenEq, eq: &Equation) -> bool {
+    let mut e: Equation = (***peq).clone();
+    e.l.m = e.l.m.iter().filter(|(s, _)| eq.l.m.contains_key(*s)).collect();
+    e.r.m = e.r.m.iter().filter(|(s, _)| eq.r.m.contains_key(*s)).collect();
+    try_match_equation(&e, eq).is_some()
+}
+
 pub fn assert_proves_equation(peq: &ProvenEq, eq: &Equation) {
     let mut e: Equation = (***peq).clone();
 
@@ -2,8 +2,9 @@ use std::cell::RefCell;
 use std::rc::Rc;
 use crate::*;
 
+// Entries are only ever appended, so that `push` and `pop` can roll the registry back by its length.
 #[derive(Clone, Default, Debug)]
-pub struct ProofRegistry(Rc<RefCell<HashMap<Equation, ProvenEq>>>);
+pub struct ProofRegistry(Rc<RefCell<IndexMap<Equation, ProvenEq>>>);
 
 fn normalize_eq(eq: &Equation) -> Equation {
     let mut theta = SlotMap::new();
@@ -19,6 +20,31 @@ fn normalize_eq(eq: &Equation) -> Equation {
 }
 
 impl ProofRegistry {
+    pub(crate) fn proofs(&self) -> Vec<ProvenEq> {
+        self.0.borrow().values().cloned().collect()
+    }
+
+    pub(crate) fn entries(&self) -> IndexMap<Equation, ProvenEq> {
+        self.0.borrow().clone()
+    }
+
+    pub(crate) fn set_entries(&self, entries: IndexMap<Equation, ProvenEq>) {
+        *self.0.borrow_mut() = entries;
+    }
+
+    pub(crate) fn ptr_eq(&self, other: &ProofRegistry) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.0.borrow().len()
+    }
+
+    // drops the entries inserted after the registry had length `len`.
+    pub(crate) fn truncate(&self, len: usize) {
+        self.0.borrow_mut().truncate(len);
+    }
+
     // will not actually insert if the registry already has a proof for it.
     pub fn insert(&self, peq: ProvenEq) -> ProvenEq {
         let eq = normalize_eq(&peq.equ());
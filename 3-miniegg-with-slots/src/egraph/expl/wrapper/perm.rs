@@ -76,6 +76,16 @@ impl ProvenPerm {
         format!("{:?}", (&self.0, &**self.1))
     }
 
+    // Whether the proof shows that the permutation is a symmetry, like `check` does without panicking.
+    pub fn is_valid(&self) -> bool {
+        let Equation { l, r } = &**self.1;
+        let slots = self.0.keys();
+        l.id == r.id && l.m.keys() == r.m.keys() && self.0.is_perm() && {
+            let eq = Equation { l: AppliedId::new(l.id, SlotMap::identity(&slots)), r: AppliedId::new(l.id, self.0.clone()) };
+            proves_equation(&self.1, &eq)
+        }
+    }
+
     pub fn check(&self) {
         let id = self.1.l.id;
         let slots = self.0.keys();
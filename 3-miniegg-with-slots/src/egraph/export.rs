@@ -0,0 +1,47 @@
+use crate::*;
+
+use std::fmt::Write;
+
+// Helpers shared by the JSON and DOT exports.
+
+impl<L: Language> EGraph<L> {
+    // All e-classes with their e-nodes, in a deterministic order.
+    // The index of an e-node in its Vec is used to name it.
+    pub(crate) fn sorted_enodes(&self) -> Vec<(Id, Vec<L>)> {
+        let mut ids = self.ids();
+        ids.sort();
+
+        ids.into_iter().map(|i| {
+            let mut v: Vec<L> = self.enodes(i).into_iter().collect();
+            v.sort_by_key(|x| format!("{x:?}"));
+            (i, v)
+        }).collect()
+    }
+}
+
+// the operator of `x`, and its slot arguments.
+pub(crate) fn op_and_slots<L: Language>(x: &L) -> (String, Vec<Slot>) {
+    let (op, children) = x.to_op();
+    let slots = children.into_iter().filter_map(|c| match c {
+        Child::Slot(s) => Some(s),
+        Child::AppliedId(_) => None,
+    }).collect();
+    (op, slots)
+}
+
+// `s` as a quoted string literal for JSON and DOT.
+// Other control characters are escaped the JSON way, DOT shows them verbatim.
+pub(crate) fn quoted(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
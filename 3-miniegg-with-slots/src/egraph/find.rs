@@ -13,9 +13,16 @@ impl<L: Language> EGraph<L> {
     }
 
     fn unionfind_get_impl(&self, i: Id, map: &mut [(AppliedId, ProvenEq)]) -> (AppliedId, ProvenEq) {
+        // Within a scope of `push`, entries older than the scope are not updated, as `pop` couldn't undo it.
+        let writable = self.logged_unionfind_len().is_none_or(|n| i.0 >= n);
         let entry = &mut map[i.0];
 
         if entry.0.id == i {
+            if !writable {
+                let mut entry = entry.clone();
+                self.unionfind_semify_entry(&mut entry);
+                return entry;
+            }
             self.unionfind_semify_entry(entry);
             return entry.clone();
         }
@@ -30,16 +37,21 @@ impl<L: Language> EGraph<L> {
             prove_transitivity(entry.1, entry_to_leader.1, &self.proof_registry),
         );
 
-        map[i.0] = new.clone();
+        if writable {
+            map[i.0] = new.clone();
+        }
         new
     }
 
-    pub fn unionfind_set(&self, i: Id, app: AppliedId, proof: ProvenEq) {
-        if CHECKS {
-            proof.check(self);
+    pub fn unionfind_set(&mut self, i: Id, app: AppliedId, proof: ProvenEq) {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(i, proof.l.id);
             assert_eq!(app.id, proof.r.id);
         }
+        if self.check_level >= CheckLevel::PerRebuild {
+            proof.check(self);
+        }
+        self.log_unionfind_entry(i);
         let mut lock = self.unionfind.try_lock().unwrap();
         if lock.len() == i.0 {
             lock.push((app, proof));
@@ -0,0 +1,153 @@
+use crate::*;
+
+// Something that happened inside of the e-graph.
+// Events are emitted while the e-graph is being modified, so its invariants might not hold at that point.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event {
+    // A new e-class was allocated.
+    Created { id: Id, slots: HashSet<Slot> },
+
+    // The e-class `from` was merged into another e-class: `from` = `into`.
+    Merged { from: Id, into: AppliedId },
+
+    // Some slots of the e-class `id` turned out to be redundant.
+    Shrunk { id: Id, redundant: HashSet<Slot> },
+
+    // The e-class `id` gained the symmetry `perm`, i.e. id[identity] = id[perm].
+    Symmetry { id: Id, perm: Perm },
+
+    // `prune` replaced the e-graph, `table` translates the old Ids to the new ones (see `translate_applied_id`).
+    // Ids missing in `table` were removed.
+    Pruned { table: HashMap<Id, AppliedId> },
+
+    // `restore` or `pop` rolled the e-graph back, so the events since then are void.
+    RolledBack,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct HookId(usize);
+
+type Hook = Box<dyn FnMut(&Event)>;
+
+#[derive(Default)]
+pub(in crate::egraph) struct Hooks {
+    next: usize,
+    hooks: Vec<(HookId, Hook)>,
+}
+
+impl Hooks {
+    // the memory of the boxed callbacks, i.e. of what they captured (but not of the heap memory owned by it).
+    pub(in crate::egraph) fn heap_size(&self) -> usize {
+        self.hooks.capacity() * std::mem::size_of::<(HookId, Hook)>()
+            + self.hooks.iter().map(|(_, f)| std::mem::size_of_val(&**f)).sum::<usize>()
+    }
+}
+
+impl std::fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Hooks({})", self.hooks.len())
+    }
+}
+
+impl<L: Language> EGraph<L> {
+    // Registers a callback, which is called for every Event from now on.
+    pub fn add_hook(&mut self, f: impl FnMut(&Event) + 'static) -> HookId {
+        let h = HookId(self.hooks.next);
+        self.hooks.next += 1;
+        self.hooks.hooks.push((h, Box::new(f)));
+        h
+    }
+
+    pub fn remove_hook(&mut self, h: HookId) {
+        self.hooks.hooks.retain(|(x, _)| *x != h);
+    }
+
+    pub(in crate::egraph) fn emit(&mut self, ev: impl FnOnce() -> Event) {
+        if self.hooks.hooks.is_empty() { return; }
+
+        let ev = ev();
+        for (_, f) in &mut self.hooks.hooks {
+            f(&ev);
+        }
+    }
+}
+
+#[test]
+fn hooks_observe_events() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut eg = EGraph::<RiseENode>::new();
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let events2 = events.clone();
+    let h = eg.add_hook(move |ev| events2.borrow_mut().push(ev.clone()));
+
+    let a = eg.add_expr(RecExpr::parse("(app (var s1) (var s2))").unwrap());
+    let b = eg.add_expr(RecExpr::parse("(app (var s2) (var s1))").unwrap());
+    let n = eg.ids().len();
+    assert_eq!(events.borrow().iter().filter(|e| matches!(e, Event::Created { .. })).count(), n);
+
+    events.borrow_mut().clear();
+    eg.union(&a, &b);
+    let ev = events.borrow().clone();
+    assert!(ev.iter().any(|e| matches!(e, Event::Symmetry { id, .. } if *id == eg.find_id(a.id))));
+
+    // symmetries found by congruence during rebuild.
+    let x = eg.add_expr(RecExpr::parse("(app (app sym_k (var s1)) (var s2))").unwrap());
+    let y = eg.add_expr(RecExpr::parse("(app (app sym_k (var s2)) (var s1))").unwrap());
+    let c = eg.add_expr(RecExpr::parse("(app sym_h (app (app sym_k (var s1)) (var s2)))").unwrap());
+    events.borrow_mut().clear();
+    eg.union(&x, &y);
+    eg.rebuild();
+    let ev = events.borrow().clone();
+    assert!(ev.iter().any(|e| matches!(e, Event::Symmetry { id, .. } if *id == eg.find_id(c.id))));
+
+    // a symmetry that is already known isn't reported again.
+    events.borrow_mut().clear();
+    eg.union(&x, &y);
+    eg.rebuild();
+    assert!(events.borrow().is_empty());
+
+    events.borrow_mut().clear();
+    let c = eg.add_expr(RecExpr::parse("(app sym_f (var s3))").unwrap());
+    let d = eg.add_expr(RecExpr::parse("(app sym_g (var s3))").unwrap());
+    eg.union(&c, &d);
+    let ev = events.borrow().clone();
+    assert!(ev.iter().any(|e| matches!(e, Event::Merged { .. })));
+
+    events.borrow_mut().clear();
+    let e = eg.add_expr(RecExpr::parse("(app sym_f (var s4))").unwrap());
+    let f = eg.add_expr(RecExpr::parse("(app sym_f (var s5))").unwrap());
+    eg.union(&e, &f);
+    let ev = events.borrow().clone();
+    assert!(ev.iter().any(|e| matches!(e, Event::Shrunk { redundant, .. } if redundant.len() == 1)));
+
+    eg.remove_hook(h);
+    events.borrow_mut().clear();
+    eg.add_expr(RecExpr::parse("(app sym_h sym_h)").unwrap());
+    assert!(events.borrow().is_empty());
+}
+
+#[test]
+fn hooks_observe_prune_and_rollback() {
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    let mut eg = EGraph::<RiseENode>::new();
+    let events = Rc::new(RefCell::new(Vec::new()));
+    let events2 = events.clone();
+    eg.add_hook(move |ev| events2.borrow_mut().push(ev.clone()));
+
+    let a = eg.add_expr(RecExpr::parse("sym_a").unwrap());
+    eg.add_expr(RecExpr::parse("sym_b").unwrap());
+
+    eg.push();
+    eg.add_expr(RecExpr::parse("sym_c").unwrap());
+    events.borrow_mut().clear();
+    eg.pop();
+    assert_eq!(*events.borrow(), vec![Event::RolledBack]);
+
+    events.borrow_mut().clear();
+    let table = eg.prune(&[a.clone()]).unwrap();
+    assert_eq!(*events.borrow(), vec![Event::Pruned { table }]);
+}
@@ -0,0 +1,95 @@
+use crate::*;
+
+use std::fmt::Write;
+
+// Exports the e-graph in the JSON format of https://github.com/egraphs-good/egraph-serialize, as read by common e-graph visualizers.
+//
+// - The e-node "k" of e-class "i" gets the node-id "i.k".
+// - The "op" is the label shown by visualizers, so it contains the operator, its slot arguments (eg. the binder of a lambda)
+//   and the slot map of each child, eg. "lam s3 [s3 -> s1]".
+//   The slot arguments and slot maps are also stored separately in the additional fields "slots" and "slotmaps".
+// - As the format lets children point to e-nodes instead of e-classes, every child points to the first e-node of its e-class.
+// - The slots of each e-class are stored in the additional field "slots" of its "class_data".
+impl<L: Language> EGraph<L> {
+    pub fn to_json(&self, roots: &[AppliedId]) -> String {
+        let nodes = self.sorted_enodes();
+
+        let mut out = String::from("{\n  \"nodes\": {");
+        let mut first = true;
+        for (i, xs) in &nodes {
+            for (k, x) in xs.iter().enumerate() {
+                let (op, slots) = op_and_slots(x);
+                let mut label = op;
+                for s in &slots {
+                    write!(label, " {s:?}").unwrap();
+                }
+                let slots: Vec<String> = slots.iter().map(|s| quoted(&s.to_string())).collect();
+                let mut child_ids = Vec::new();
+                let mut slotmaps = Vec::new();
+                for app in x.applied_id_occurences() {
+                    child_ids.push(quoted(&format!("{}.0", self.find_id(app.id).0)));
+                    write!(label, " {:?}", app.m).unwrap();
+                    slotmaps.push(quoted(&format!("{:?}", app.m)));
+                }
+
+                if !first { out.push(','); }
+                first = false;
+                write!(out, "\n    {}: {{", quoted(&format!("{}.{k}", i.0))).unwrap();
+                write!(out, "\n      \"op\": {},", quoted(&label)).unwrap();
+                write!(out, "\n      \"slots\": [{}],", slots.join(", ")).unwrap();
+                write!(out, "\n      \"children\": [{}],", child_ids.join(", ")).unwrap();
+                write!(out, "\n      \"slotmaps\": [{}],", slotmaps.join(", ")).unwrap();
+                write!(out, "\n      \"eclass\": {},", quoted(&i.0.to_string())).unwrap();
+                write!(out, "\n      \"cost\": 1.0").unwrap();
+                write!(out, "\n    }}").unwrap();
+            }
+        }
+        out.push_str("\n  },\n");
+
+        let roots: Vec<String> = roots.iter().map(|r| quoted(&self.find_id(r.id).0.to_string())).collect();
+        writeln!(out, "  \"root_eclasses\": [{}],", roots.join(", ")).unwrap();
+
+        out.push_str("  \"class_data\": {");
+        for (n, (i, _)) in nodes.iter().enumerate() {
+            let mut slots: Vec<Slot> = self.slots(*i).into_iter().collect();
+            slots.sort();
+            let slots: Vec<String> = slots.iter().map(|s| quoted(&s.to_string())).collect();
+            if n > 0 { out.push(','); }
+            write!(out, "\n    {}: {{ \"slots\": [{}] }}", quoted(&i.0.to_string()), slots.join(", ")).unwrap();
+        }
+        out.push_str("\n  }\n}\n");
+        out
+    }
+}
+
+#[test]
+fn json_export() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(lam s0 (app (var s0) sym_x))").unwrap());
+    eg.add_expr(RecExpr::parse("(app sym_\"q sym_x)").unwrap());
+    let json = eg.to_json(&[i.clone()]);
+
+    // one entry per e-node.
+    assert_eq!(json.matches("\"eclass\"").count(), eg.total_number_of_nodes());
+    assert!(json.contains(&format!("\"root_eclasses\": [\"{}\"]", i.id.0)));
+
+    // the rendered label shows the binder and the slot maps of the children.
+    let lam = eg.enodes(i.id).into_iter().next().unwrap();
+    let RiseENode::Lam(x, b) = &lam else { panic!() };
+    let label = format!("lam {x:?} {:?}", b.m);
+    assert!(label.contains(&format!(" -> {x:?}]")));
+    assert!(json.contains(&format!("\"op\": \"{label}\",\n      \"slots\": [\"{x:?}\"]")));
+    let v = eg.lookup(&RiseENode::Var(*x)).unwrap();
+    let RiseENode::Var(v) = eg.enodes(v.id).into_iter().next().unwrap() else { panic!() };
+    assert!(json.contains(&format!("\"op\": \"var {v:?}\"")));
+
+    // quotes in ops are escaped, nodes without slots have a plain label.
+    assert!(json.contains("\"op\": \"sym_\\\"q\""));
+
+    // every child refers to an existing node.
+    for l in json.lines().filter(|l| l.trim().starts_with("\"children\"")) {
+        for c in l.split('"').skip(3).step_by(2) {
+            assert!(json.contains(&format!("\"{c}\": {{")));
+        }
+    }
+}
@@ -0,0 +1,238 @@
+use crate::*;
+
+use super::snapshot::Scope;
+use std::mem::size_of;
+
+// Estimated memory consumption of the e-graph, in bytes per component.
+// Every component contains the memory of its containers and of everything they own.
+// Proofs are shared between components, so they are only counted in `proofs`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MemoryReport {
+    pub unionfind: usize,
+
+    // the EClass structs, including their slot sets and syn_enodes.
+    pub classes: usize,
+
+    // the e-nodes stored in the classes, together with their bijections.
+    pub nodes: usize,
+    pub usages: usize,
+    pub groups: usize,
+    pub hashcons: usize,
+    pub syn_hashcons: usize,
+    pub pending: usize,
+    pub touched: usize,
+
+    // all proofs reachable from the e-graph, including the proof registry.
+    pub proofs: usize,
+
+    // what the open `push` scopes recorded to undo their changes.
+    pub scopes: usize,
+
+    // the registered callbacks.
+    pub hooks: usize,
+
+    // The part of all the above that is spent on the contents of SlotMaps.
+    // This is not counted separately in `total`.
+    pub slotmaps: usize,
+}
+
+impl MemoryReport {
+    pub fn total(&self) -> usize {
+        self.unionfind + self.classes + self.nodes + self.usages + self.groups
+            + self.hashcons + self.syn_hashcons + self.pending + self.touched + self.proofs
+            + self.scopes + self.hooks
+    }
+}
+
+impl std::fmt::Display for MemoryReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total = self.total();
+        let rows = [
+            ("unionfind", self.unionfind),
+            ("classes", self.classes),
+            ("nodes", self.nodes),
+            ("usages", self.usages),
+            ("groups", self.groups),
+            ("hashcons", self.hashcons),
+            ("syn_hashcons", self.syn_hashcons),
+            ("pending", self.pending),
+            ("touched", self.touched),
+            ("proofs", self.proofs),
+            ("scopes", self.scopes),
+            ("hooks", self.hooks),
+        ];
+        for (name, bytes) in rows {
+            let percent = if total == 0 { 0.0 } else { 100.0 * bytes as f64 / total as f64 };
+            writeln!(f, "{name:<14}{bytes:>14} B {percent:>5.1}%")?;
+        }
+        writeln!(f, "{:<14}{total:>14} B", "total")?;
+        writeln!(f, "{:<14}{:>14} B", "(slotmaps)", self.slotmaps)
+    }
+}
+
+impl<L: Language> EGraph<L> {
+    // This walks over the whole e-graph, so it's linear in its size.
+    // Snapshots are owned by the caller, so they are not included.
+    pub fn memory_report(&self) -> MemoryReport {
+        let mut r = MemoryReport::default();
+
+        // SlotMap contents are accumulated in `sz.slotmaps` as a side effect.
+        let mut sz = Sizer::default();
+
+        {
+            let uf = self.unionfind.lock().unwrap();
+            r.unionfind = uf.capacity() * size_of::<(AppliedId, ProvenEq)>()
+                        + uf.iter().map(|(a, _)| sz.slotmap(&a.m)).sum::<usize>();
+        }
+
+        r.classes = hash_table_size::<Id, EClass<L>>(self.classes.capacity());
+        for c in self.classes.values() {
+            let (class, nodes, usages, groups) = sz.class(c);
+            r.classes += class;
+            r.nodes += nodes;
+            r.usages += usages;
+            r.groups += groups;
+        }
+
+        r.hashcons = hash_table_size::<L, Id>(self.hashcons.capacity());
+        for sh in self.hashcons.keys() {
+            r.hashcons += sz.enode(sh);
+        }
+
+        r.syn_hashcons = hash_table_size::<L, AppliedId>(self.syn_hashcons.capacity());
+        for (sh, a) in &self.syn_hashcons {
+            r.syn_hashcons += sz.enode(sh) + sz.slotmap(&a.m);
+        }
+
+        r.pending = sz.pending(&self.pending);
+
+        r.touched = hash_table_size::<Id, usize>(self.touched.capacity());
+
+        let registry = self.proof_registry.proofs();
+        r.proofs = hash_table_size::<Equation, ProvenEq>(registry.len());
+        let mut stack: Vec<ProvenEq> = registry.iter().map(|p| {
+            r.proofs += sz.slotmap(&p.l.m) + sz.slotmap(&p.r.m);
+            p.clone()
+        }).collect();
+        stack.extend(self.unionfind.lock().unwrap().iter().map(|(_, p)| p.clone()));
+        for c in self.classes.values() {
+            stack.push(c.redundancy_proof.clone());
+            stack.extend(c.group.generators().into_iter().map(|p| p.1));
+        }
+        let mut visited: HashSet<*const ProvenEqRaw> = HashSet::default();
+        while let Some(p) = stack.pop() {
+            if !visited.insert(Arc::as_ptr(&p)) { continue; }
+
+            // the two reference counts of the Arc.
+            r.proofs += 2 * size_of::<usize>() + size_of::<ProvenEqRaw>() + sz.slotmap(&p.l.m) + sz.slotmap(&p.r.m);
+            match p.proof() {
+                Proof::Explicit(ExplicitProof(Some(j))) => r.proofs += j.capacity(),
+                Proof::Congruence(CongruenceProof(xs)) => r.proofs += xs.capacity() * size_of::<ProvenEq>(),
+                _ => {},
+            }
+            stack.extend(p.subproofs().into_iter().cloned());
+        }
+
+        // the proofs in the scopes are shared with the e-graph, so they are already counted above.
+        r.scopes = self.scopes.capacity() * size_of::<Scope<L>>()
+                 + self.scopes.iter().map(|s| s.heap_size(&mut sz)).sum::<usize>();
+
+        r.hooks = self.hooks.heap_size();
+
+        r.slotmaps = sz.slotmaps;
+        r
+    }
+}
+
+// Measures the heap memory of parts of the e-graph, and accumulates the part of it that is spent on SlotMap contents.
+#[derive(Default)]
+pub(in crate::egraph) struct Sizer {
+    slotmaps: usize,
+}
+
+impl Sizer {
+    pub(in crate::egraph) fn slotmap(&mut self, m: &SlotMap) -> usize {
+        let x = m.len() * size_of::<(Slot, Slot)>();
+        self.slotmaps += x;
+        x
+    }
+
+    // the slot maps of the children of `x`.
+    pub(in crate::egraph) fn enode<L: Language>(&mut self, x: &L) -> usize {
+        x.applied_id_occurences().iter().map(|a| self.slotmap(&a.m)).sum()
+    }
+
+    // the heap memory of `c` as (class, nodes, usages, groups), see MemoryReport.
+    pub(in crate::egraph) fn class<L: Language>(&mut self, c: &EClass<L>) -> (usize, usize, usize, usize) {
+        let class = hash_table_size::<Slot, ()>(c.slots.capacity()) + self.enode(&c.syn_enode);
+
+        let mut nodes = hash_table_size::<L, (Bijection, AppliedId)>(c.nodes.capacity());
+        for (sh, (bij, src)) in &c.nodes {
+            nodes += self.enode(sh) + self.slotmap(bij) + self.slotmap(&src.m);
+        }
+
+        let mut usages = hash_table_size::<L, ()>(c.usages.capacity());
+        for sh in &c.usages {
+            usages += self.enode(sh);
+        }
+
+        let groups = c.group.heap_size(&|p: &ProvenPerm| p.0.len() * size_of::<(Slot, Slot)>());
+
+        (class, nodes, usages, groups)
+    }
+
+    pub(in crate::egraph) fn pending<L: Language>(&mut self, pending: &IndexSet<L>) -> usize {
+        pending.capacity() * (size_of::<L>() + 2 * size_of::<usize>())
+            + pending.iter().map(|sh| self.enode(sh)).sum::<usize>()
+    }
+}
+
+// An estimate of the memory of a hash table with the given capacity:
+// the buckets are rounded up to a power of two, and every bucket has an additional control byte.
+pub(crate) fn hash_table_size<K, V>(capacity: usize) -> usize {
+    if capacity == 0 { return 0; }
+    let buckets = (capacity * 8 / 7).next_power_of_two();
+    buckets * (size_of::<(K, V)>() + 1)
+}
+
+#[test]
+fn memory_report_grows() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let empty = eg.memory_report();
+    eg.add_expr(RecExpr::parse("(lam s0 (app (var s0) sym_x))").unwrap());
+    let small = eg.memory_report();
+    do_rewrites(&mut eg, &rise_rules(SubstMethod::SmallStep));
+    let big = eg.memory_report();
+
+    assert!(empty.total() < small.total());
+    assert!(small.total() < big.total());
+    assert!(small.nodes > 0 && small.hashcons > 0 && small.slotmaps > 0);
+    assert!(big.slotmaps < big.total());
+
+    // a symmetric class has a non-trivial stabilizer chain.
+    let a = eg.add_expr(RecExpr::parse("(app (var s1) (var s2))").unwrap());
+    let b = eg.add_expr(RecExpr::parse("(app (var s2) (var s1))").unwrap());
+    let before = eg.memory_report().groups;
+    eg.union(&a, &b);
+    assert!(eg.memory_report().groups > before);
+    assert!(eg.memory_report().to_string().contains("total"));
+}
+
+#[test]
+fn memory_report_counts_scopes_and_hooks() {
+    let mut eg = EGraph::<RiseENode>::new();
+    eg.add_expr(RecExpr::parse("(lam s0 (app (var s0) sym_x))").unwrap());
+    let before = eg.memory_report();
+    assert_eq!((before.scopes, before.hooks), (0, 0));
+
+    eg.push();
+    do_rewrites(&mut eg, &rise_rules(SubstMethod::SmallStep));
+    assert!(eg.memory_report().scopes > 0);
+
+    let data = [0u64; 16];
+    eg.add_hook(move |_| { let _ = data.len(); });
+    assert!(eg.memory_report().hooks >= 16 * size_of::<u64>());
+
+    eg.pop();
+    assert_eq!(eg.memory_report().scopes, eg.scopes.capacity() * size_of::<Scope<RiseENode>>());
+}
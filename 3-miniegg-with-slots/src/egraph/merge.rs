@@ -0,0 +1,231 @@
+use crate::*;
+
+impl<L: Language> EGraph<L> {
+    // Imports all e-nodes, unions, symmetries and redundant slots of `other` into `self`.
+    //
+    // Returns the translation table: the e-class `c` of `other` corresponds to `table[&c]` in `self`,
+    // where the slots of `table[&c]` are slots of `c`. Hence `c(m)` translates to `table[&c].apply_slotmap(&m)`, see `translate_applied_id`.
+    // The table contains every Id of `other`, including the non-leaders.
+    //
+    // Fails without changing `self`, if some classes of `other` don't contain a finite term.
+    pub fn merge(&mut self, other: &EGraph<L>) -> Result<HashMap<Id, AppliedId>, NoFiniteTerm> {
+        let nodes = other.class_nodes();
+        let order = other.term_order(&nodes)?;
+        Ok(self.import(other, &nodes, &order, "merge"))
+    }
+
+    // The e-nodes of each e-class, using the slot names of the class.
+    pub(in crate::egraph) fn class_nodes(&self) -> HashMap<Id, Vec<L>> {
+        self.ids().into_iter().map(|c| {
+            let mut v: Vec<L> = self.enodes_applied(&self.mk_sem_identity_applied_id(c)).into_iter().collect();
+            v.sort_by_key(|x| format!("{:?}", x.weak_shape().0));
+            (c, v)
+        }).collect()
+    }
+
+    // Orders the classes of `nodes`, each with an e-node whose children are earlier in the order.
+    // A class becomes ready once all children of one of its e-nodes are ready, so this is linear in the number of children.
+    pub(in crate::egraph) fn term_order<'a>(&self, nodes: &'a HashMap<Id, Vec<L>>) -> Result<Vec<(Id, &'a L)>, NoFiniteTerm> {
+        let mut ids: Vec<Id> = nodes.keys().copied().collect();
+        ids.sort();
+
+        // for each e-node, the number of its child classes that are not ready yet.
+        let mut missing: HashMap<(Id, usize), usize> = HashMap::default();
+        let mut parents: HashMap<Id, Vec<(Id, usize)>> = HashMap::default();
+        let mut stack: Vec<(Id, usize)> = Vec::new();
+        for c in &ids {
+            for (k, x) in nodes[c].iter().enumerate() {
+                let children: HashSet<Id> = x.ids().into_iter().map(|j| self.find_id(j)).collect();
+                let mut children: Vec<Id> = children.into_iter().collect();
+                children.sort();
+                for j in &children {
+                    parents.entry(*j).or_default().push((*c, k));
+                }
+                missing.insert((*c, k), children.len());
+                if children.is_empty() { stack.push((*c, k)); }
+            }
+        }
+        stack.reverse();
+
+        let mut done: HashSet<Id> = HashSet::default();
+        let mut order = Vec::new();
+        while let Some((c, k)) = stack.pop() {
+            if !done.insert(c) { continue; }
+            order.push((c, &nodes[&c][k]));
+            for (p, pk) in parents.remove(&c).unwrap_or_default() {
+                let m = missing.get_mut(&(p, pk)).unwrap();
+                *m -= 1;
+                if *m == 0 { stack.push((p, pk)); }
+            }
+        }
+
+        if order.len() < ids.len() {
+            return Err(NoFiniteTerm(ids.into_iter().filter(|c| !done.contains(c)).collect()));
+        }
+        Ok(order)
+    }
+
+    // Imports the given e-nodes of `other`, as returned by `class_nodes`, together with the symmetries and redundant slots of their classes.
+    // Children of these e-nodes have to be among the imported classes, and `order` is their `term_order`.
+    pub(in crate::egraph) fn import(&mut self, other: &EGraph<L>, nodes: &HashMap<Id, Vec<L>>, order: &[(Id, &L)], justification: &str) -> HashMap<Id, AppliedId> {
+        let mut ids: Vec<Id> = nodes.keys().copied().collect();
+        ids.sort();
+        let j = || Some(justification.to_string());
+
+        // 1. Every class obtains a representative, using an e-node whose children are already translated.
+        let mut table: HashMap<Id, AppliedId> = HashMap::default();
+        for (c, x) in order {
+            let a = self.add(translate_enode(x, other, &table));
+            let a = self.restrict_slots(a, &other.slots(*c), justification);
+            table.insert(*c, a);
+        }
+
+        // 2. The remaining e-nodes are added to their classes.
+        for c in &ids {
+            for x in &nodes[c] {
+                let b = self.add(translate_enode(x, other, &table));
+                self.union_justified(&table[c], &b, j());
+            }
+        }
+
+        // 3. Symmetries.
+        for c in &ids {
+            for p in other.classes[c].group.generators() {
+                let a = &table[c];
+                self.union_justified(a, &a.apply_slotmap(&p.to_slotmap()), j());
+            }
+        }
+
+        // 4. Slots that became redundant in `other` by a union that we didn't replicate.
+        for c in &ids {
+            let a = self.find_applied_id(&table[c]);
+            let a = self.restrict_slots(a, &other.slots(*c), justification);
+            table.insert(*c, a);
+        }
+
+        for (i, app) in other.unionfind_iter() {
+            if i == app.id { continue; }
+            if let Some(a) = table.get(&app.id) {
+                let a = a.apply_slotmap(&app.m);
+                table.insert(i, a);
+            }
+        }
+
+        for a in table.values_mut() {
+            *a = self.find_applied_id(a);
+        }
+
+        if self.check_level >= CheckLevel::PerRebuild { self.check(); }
+
+        table
+    }
+
+    // makes all slots of `a` outside of `slots` redundant.
+    fn restrict_slots(&mut self, a: AppliedId, slots: &HashSet<Slot>, justification: &str) -> AppliedId {
+        let extra = &a.slots() - slots;
+        if extra.is_empty() { return a; }
+
+        let fresh = SlotMap::bijection_from_fresh_to(&extra).inverse();
+        let m = SlotMap::identity(&(&a.slots() & slots)).union(&fresh);
+        let b = a.apply_slotmap(&m);
+        self.union_justified(&a, &b, Some(justification.to_string()));
+        self.find_applied_id(&a)
+    }
+}
+
+// The classes that don't contain a finite term, as their e-nodes only form cycles.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct NoFiniteTerm(pub Vec<Id>);
+
+// Translates an AppliedId of the e-graph `other` into the merged e-graph, using the table returned by `merge`.
+pub fn translate_applied_id(app: &AppliedId, table: &HashMap<Id, AppliedId>) -> AppliedId {
+    table[&app.id].apply_slotmap(&app.m)
+}
+
+fn translate_enode<L: Language>(x: &L, other: &EGraph<L>, table: &HashMap<Id, AppliedId>) -> L {
+    let mut x = x.clone();
+    for a in x.applied_id_occurences_mut() {
+        *a = translate_applied_id(&other.find_applied_id(a), table);
+    }
+    x
+}
+
+#[test]
+fn merge_egraphs() {
+    let p = |s| RecExpr::<RiseENode>::parse(s).unwrap();
+
+    let mut eg = EGraph::<RiseENode>::new();
+    let f = eg.add_expr(p("(app (var s0) (lam s1 (var s1)))"));
+
+    let mut other = EGraph::<RiseENode>::new();
+    let a = other.add_expr(p("(app (var s0) (lam s1 (var s1)))"));
+    let b = other.add_expr(p("(app sym_g (var s0))"));
+    other.union(&a, &b);
+
+    // a symmetry.
+    let c = other.add_expr(p("(app (app sym_h (var s1)) (var s2))"));
+    let d = other.add_expr(p("(app (app sym_h (var s2)) (var s1))"));
+    other.union(&c, &d);
+
+    // a redundant slot.
+    let e = other.add_expr(p("(app (app sym_k (var s3)) (var s4))"));
+    let e2 = other.add_expr(p("(app (app sym_k (var s3)) (var s5))"));
+    other.union(&e, &e2);
+
+    let table = eg.merge(&other).unwrap();
+    eg.check();
+
+    for x in other.unionfind_iter().map(|(i, _)| i) {
+        assert!(table.contains_key(&x));
+    }
+
+    let g = eg.add_expr(p("(app sym_g (var s0))"));
+    assert!(eg.eq(&f, &g));
+    assert!(eg.eq(&translate_applied_id(&a, &table), &f));
+
+    assert!(eg.eq(&translate_applied_id(&c, &table), &translate_applied_id(&d, &table)));
+    let c = eg.add_expr(p("(app (app sym_h (var s1)) (var s2))"));
+    let d = eg.add_expr(p("(app (app sym_h (var s2)) (var s1))"));
+    assert!(eg.eq(&c, &d));
+
+    let e = translate_applied_id(&e, &table);
+    assert_eq!(e.slots(), [Slot::new(3)].into_iter().collect());
+    let e2 = eg.add_expr(p("(app (app sym_k (var s3)) (var s6))"));
+    assert!(eg.eq(&e, &e2));
+}
+
+#[test]
+fn merge_into_empty() {
+    let p = |s| RecExpr::parse(s).unwrap();
+    let mut other = EGraph::<RiseENode>::new();
+
+    // a symmetric class, a class with a redundant slot, and a binder.
+    let a = other.add_expr(p("(app (var s1) (var s2))"));
+    let b = other.add_expr(p("(app (var s2) (var s1))"));
+    other.union(&a, &b);
+    let c = other.add_expr(p("(app (var s3) sym_x)"));
+    let d = other.add_expr(p("sym_y"));
+    other.union(&c, &d);
+    other.add_expr(p("(lam s4 (app (app (var s4) (var s5)) sym_y))"));
+
+    let mut eg = EGraph::new();
+    eg.merge(&other).unwrap();
+    eg.check();
+    assert_eq!(eg.ids().len(), other.ids().len());
+    assert_eq!(eg.total_number_of_nodes(), other.total_number_of_nodes());
+}
+
+#[test]
+fn merge_without_finite_term() {
+    let mut other = EGraph::<RiseENode>::new();
+    let x = other.add_expr(RecExpr::parse("sym_x").unwrap());
+    let y = other.add(RiseENode::App(x.clone(), x.clone()));
+    other.union(&x, &y);
+
+    // only the cyclic e-node is imported.
+    let c = other.find_id(x.id);
+    let mut nodes = other.class_nodes();
+    nodes.get_mut(&c).unwrap().retain(|n| matches!(n, RiseENode::App(..)));
+
+    assert_eq!(other.term_order(&nodes).map(|_| ()), Err(NoFiniteTerm(vec![c])));
+}
@@ -15,6 +15,48 @@ pub use expl::*;
 mod check;
 pub use check::*;
 
+mod serialize;
+pub use serialize::*;
+
+mod export;
+pub use export::*;
+
+mod json;
+pub use json::*;
+
+mod dot;
+pub use dot::*;
+
+mod snapshot;
+pub use snapshot::*;
+
+mod merge;
+pub use merge::*;
+
+mod prune;
+pub use prune::*;
+
+mod hooks;
+pub use hooks::*;
+
+mod memory;
+pub use memory::*;
+
+mod stats;
+pub use stats::*;
+
+mod diff;
+pub use diff::*;
+
+mod budget;
+pub use budget::*;
+
+mod cancel;
+pub use cancel::*;
+
+mod symmetry;
+pub use symmetry::*;
+
 use std::sync::Mutex;
 
 /// Each E-Class can be understood "semantically" or "syntactically":
@@ -71,11 +113,62 @@ pub struct EGraph<L: Language> {
     syn_hashcons: HashMap<L, AppliedId>,
 
     // E-Nodes that need to be re-processed, stored as shapes.
-    pending: HashSet<L>,
+    pending: IndexSet<L>,
+
+    // For every class that got created or modified, the generation in which this last happened.
+    // Consumers remember a generation to find out what changed since then.
+    // Only the last modification is stored, so this doesn't grow beyond the number of ids.
+    touched: HashMap<Id, usize>,
+    generation: usize,
+
+    // Bumped whenever classes might lose e-nodes (see `restore`).
+    // Whatever was computed from `touched_since` in an older epoch needs to be recomputed.
+    epoch: usize,
+
+    // The scopes opened by `push`, innermost last.
+    scopes: Vec<Scope<L>>,
+
+    // Callbacks registered by `add_hook`.
+    hooks: Hooks,
+
+    check_level: CheckLevel,
+
+    // Slot::fresh() draws from this allocator within the e-graph's operations.
+    slot_allocator: SlotAllocator,
+
+    budget: Budget,
+
+    // The last memory measurement as (number of e-nodes, bytes), see `estimated_memory`.
+    memory_estimate: std::cell::Cell<(usize, usize)>,
+
+    cancel_token: CancelToken,
+    deadline: Option<std::time::Instant>,
 
     proof_registry: ProofRegistry,
 }
 
+// How much invariant-checking the e-graph does at runtime, each level includes the previous ones.
+// Checks outside of the e-graph (eg. in SlotMap) are still controlled by the `checks` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum CheckLevel {
+    Off,
+
+    // assertions local to the current operation.
+    Cheap,
+
+    // a full `check` for every `rebuild` (and every extraction), and checking proofs and group-compatible variants.
+    PerRebuild,
+
+    // a full `check` for every processed pending e-node.
+    PerPending,
+}
+
+impl Default for CheckLevel {
+    fn default() -> Self {
+        if CHECKS { CheckLevel::PerPending } else { CheckLevel::Off }
+    }
+}
+
 impl<L: Language> EGraph<L> {
     pub fn new() -> Self {
         EGraph {
@@ -84,10 +177,60 @@ impl<L: Language> EGraph<L> {
             hashcons: Default::default(),
             syn_hashcons: Default::default(),
             pending: Default::default(),
+            touched: Default::default(),
+            generation: 0,
+            epoch: 0,
+            scopes: Vec::new(),
+            hooks: Hooks::default(),
+            check_level: CheckLevel::default(),
+            slot_allocator: SlotAllocator::new(),
+            budget: Budget::default(),
+            memory_estimate: Default::default(),
+            cancel_token: CancelToken::default(),
+            deadline: None,
             proof_registry: ProofRegistry::default(),
         }
     }
 
+    pub fn check_level(&self) -> CheckLevel {
+        self.check_level
+    }
+
+    pub fn set_check_level(&mut self, level: CheckLevel) {
+        self.check_level = level;
+    }
+
+    pub fn slot_allocator(&self) -> &SlotAllocator {
+        &self.slot_allocator
+    }
+
+    // Should be called before adding anything, as the slots of the e-graph could otherwise collide with the new ones.
+    pub fn set_slot_allocator(&mut self, a: SlotAllocator) {
+        self.slot_allocator = a;
+    }
+
+    // Runs `f`, such that Slot::fresh() draws from the allocator of this e-graph.
+    pub(crate) fn with_slot_allocator<R>(&mut self, f: impl FnOnce(&mut Self) -> R) -> R {
+        let a = self.slot_allocator.clone();
+        a.scope(|| f(self))
+    }
+
+    // A marker for the current state of the e-graph, to be passed to `touched_since` later.
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    // Generations are only comparable within the same epoch.
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    // Returns all classes that were created or modified since `generation`.
+    // These might be dead by now, use find_id to obtain their leaders.
+    pub fn touched_since(&self, generation: usize) -> HashSet<Id> {
+        self.touched.iter().filter(|(_, g)| **g >= generation).map(|(i, _)| *i).collect()
+    }
+
     pub fn slots(&self, id: Id) -> HashSet<Slot> {
         self.classes[&id].slots.clone()
     }
@@ -96,6 +239,7 @@ impl<L: Language> EGraph<L> {
         self.classes[&id].syn_enode.slots()
     }
 
+    // The leader ids, in increasing order.
     pub fn ids(&self) -> Vec<Id> {
         self.unionfind_iter()
                        .filter(|(x, y)| x == &y.id)
@@ -112,6 +256,10 @@ impl<L: Language> EGraph<L> {
 
     // Generates fresh slots for redundant slots.
     pub fn enodes_applied(&self, i: &AppliedId) -> HashSet<L> {
+        self.slot_allocator.scope(|| self.enodes_applied_impl(i))
+    }
+
+    fn enodes_applied_impl(&self, i: &AppliedId) -> HashSet<L> {
         let i = self.find_applied_id(i);
 
         let mut out = HashSet::default();
@@ -131,7 +279,7 @@ impl<L: Language> EGraph<L> {
             out.insert(x.apply_slotmap(&m));
         }
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::PerRebuild {
             for x in &out {
                 assert!(self.eq(&self.lookup(x).unwrap(), &i));
             }
@@ -149,7 +297,7 @@ impl<L: Language> EGraph<L> {
         let a = self.find_applied_id(a);
         let b = self.find_applied_id(b);
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             self.check_sem_applied_id(&a);
             self.check_sem_applied_id(&b);
         }
@@ -159,7 +307,7 @@ impl<L: Language> EGraph<L> {
         let id = a.id;
 
         let perm = a.m.compose(&b.m.inverse());
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert!(perm.is_perm());
             assert_eq!(&perm.values(), &self.classes[&id].slots);
         }
@@ -178,12 +326,18 @@ impl<L: Language> EGraph<L> {
     }
 
     // converts l to its class normal form, so that calling lookup on it yields the identity AppliedId.
+    // The slots of l that are redundant in its class are not covered by the lookup, they are replaced by fresh ones.
+    // Hence class_nf works for every e-node of the e-graph, also after some of its slots became redundant.
     pub fn class_nf(&self, l: &L) -> L {
+        self.slot_allocator.scope(|| self.class_nf_impl(l))
+    }
+
+    fn class_nf_impl(&self, l: &L) -> L {
         let l = self.refresh_internals(l);
         let i = self.lookup(&l).unwrap();
-        let l = l.apply_slotmap(&i.m);
+        let l = l.apply_slotmap_fresh(&i.m);
 
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert!(self.lookup(&l).unwrap().m.iter().all(|(x, y)| x == y));
         }
 
@@ -272,7 +426,7 @@ impl<L: Language> EGraph<L> {
     // TODO every usage of this function hurts performance drastically. Which of them can I eliminate?
     pub fn proven_get_group_compatible_variants(&self, enode: &L) -> HashSet<(L, Vec<ProvenEq>)> {
         // should only be called with an up-to-date e-node.
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             for x in enode.applied_id_occurences() {
                 assert!(self.is_alive(x.id));
             }
@@ -283,7 +437,7 @@ impl<L: Language> EGraph<L> {
 
         // the proofs in `s` should express how its node changed relative to `enode`.
         let s_inv = |s: &HashSet<(L, Vec<ProvenEq>)>| {
-            if CHECKS {
+            if self.check_level >= CheckLevel::PerRebuild {
                 for (new_enode, prfs) in s {
                     for i in 0..n {
                         let l = enode.applied_id_occurences()[i].clone();
@@ -305,33 +459,64 @@ impl<L: Language> EGraph<L> {
 
         s_inv(&s);
 
+        // every perm of a group is a product of coset representatives, one per level of its stabilizer chain.
+        // So applying the representatives level by level yields all variants.
+        // There are still |G| of them for each child, as the shape is the minimum over all of them.
         for (i, app_id) in enode.applied_id_occurences().iter().enumerate() {
-            let grp_perms = self.classes[&app_id.id].group.all_perms();
-            let mut next = HashSet::default();
-            s_inv(&s);
-            for (x, x_prfs) in s {
-                for proven_perm in &grp_perms {
-                    proven_perm.check();
-                    let x_i = x.applied_id_occurences()[i].clone();
-                    let x_prfs_i = x_prfs[i].clone();
-                    let (app_id, prf) = self.apply_proven_perm((x_i, x_prfs_i), proven_perm);
-
-                    let mut x2 = x.clone();
-                    *x2.applied_id_occurences_mut()[i] = app_id;
-
-                    let mut x_prfs2 = x_prfs.clone();
-                    x_prfs2[i] = prf;
-
-                    next.insert((x2, x_prfs2));
+            let levels = self.classes[&app_id.id].group.transversals();
+            for (reps, _) in levels {
+                let mut next = HashSet::default();
+                s_inv(&s);
+                for (x, x_prfs) in s {
+                    for proven_perm in &reps {
+                        if self.check_level >= CheckLevel::PerRebuild {
+                            proven_perm.check();
+                        }
+                        let x_i = x.applied_id_occurences()[i].clone();
+                        let x_prfs_i = x_prfs[i].clone();
+                        let (app_id, prf) = self.apply_proven_perm((x_i, x_prfs_i), proven_perm);
+
+                        let mut x2 = x.clone();
+                        *x2.applied_id_occurences_mut()[i] = app_id;
+
+                        let mut x_prfs2 = x_prfs.clone();
+                        x_prfs2[i] = prf;
+
+                        next.insert((x2, x_prfs2));
+                    }
                 }
+                s = next;
+                s_inv(&s);
             }
-            s = next;
-            s_inv(&s);
         }
 
         s
     }
 
+    // Visits the AppliedIds that `a` is equal to by the symmetries of its class, i.e. `a.id` applied to "p a.m" for each p in its group.
+    // They are expanded along the stabilizer chain, where each level decides the egraph slots `x` that some slots `s` of the class are mapped to.
+    // If `accept(s, x)` returns false for one of them, none of the variants extending this choice are visited.
+    pub fn visit_applied_id_variants(&self, a: &AppliedId, mut accept: impl FnMut(Slot, Slot) -> bool, mut f: impl FnMut(AppliedId)) {
+        let a = self.find_applied_id(a);
+        let levels = self.classes[&a.id].group.transversals();
+        let identity = SlotMap::identity(&self.slots(a.id));
+        self.visit_variants_impl(&a, &levels, identity, &mut accept, &mut f);
+    }
+
+    // `acc` is the product of the representatives chosen so far, it already maps the base points of these levels to their final images.
+    fn visit_variants_impl(&self, a: &AppliedId, levels: &[(Vec<&ProvenPerm>, Vec<Slot>)], acc: Perm, accept: &mut impl FnMut(Slot, Slot) -> bool, f: &mut impl FnMut(AppliedId)) {
+        let Some(((reps, determined), rest)) = levels.split_first() else {
+            f(self.mk_sem_applied_id(a.id, acc.compose(&a.m)));
+            return;
+        };
+        for u in reps {
+            let acc = u.0.compose(&acc);
+            if determined.iter().all(|s| accept(*s, a.m[acc[*s]])) {
+                self.visit_variants_impl(a, rest, acc, accept, f);
+            }
+        }
+    }
+
     pub fn get_group_compatible_variants(&self, enode: &L) -> HashSet<L> {
         self.proven_get_group_compatible_variants(enode).into_iter().map(|(x, _)| x).collect()
     }
@@ -393,3 +578,50 @@ impl<L: Language> EGraph<L> {
         }
     }
 }
+
+#[test]
+fn class_nf_redundant_slots() {
+    // the e-node of the class still refers to the now redundant slot of its child.
+    let mut eg = EGraph::<RiseENode>::new();
+    let c = eg.add_expr(RecExpr::parse("(app sym_f (var s3))").unwrap());
+    let d = eg.add_expr(RecExpr::parse("(app sym_f (var s4))").unwrap());
+    eg.union(&c, &d);
+
+    let i = eg.find_id(c.id);
+    for x in eg.enodes(i) {
+        let x = eg.class_nf(&x);
+        assert_eq!(eg.lookup(&x).unwrap(), eg.mk_sem_identity_applied_id(i));
+    }
+
+    // the redundant slot gets a fresh name, the other slots keep the names of the class.
+    let mut eg = EGraph::<RiseENode>::new();
+    let a = eg.add_expr(RecExpr::parse("(app (var s1) (var s2))").unwrap());
+    let b = eg.add_expr(RecExpr::parse("(app sym_g (var s2))").unwrap());
+    eg.union(&a, &b);
+    let i = eg.find_id(a.id);
+    let x = eg.enodes(i).into_iter().find(|x| x.slots().len() == 2).unwrap();
+    let nf = eg.class_nf(&x);
+    assert_eq!(eg.lookup(&nf).unwrap(), eg.mk_sem_identity_applied_id(i));
+    assert!(nf.slots().is_superset(&eg.slots(i)));
+    let redundant = &x.slots() - &eg.slots(i);
+    let fresh = &nf.slots() - &eg.slots(i);
+    assert_eq!((redundant.len(), fresh.len()), (1, 1));
+    assert_ne!(redundant, fresh);
+}
+
+#[test]
+fn touched_is_bounded() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let a = eg.add_expr(RecExpr::parse("(app (var s1) (var s2))").unwrap());
+    let g = eg.generation();
+    for i in 0..50 {
+        let b = eg.add_expr(RecExpr::parse(&format!("(app sym_x{i} sym_y)")).unwrap());
+        eg.union(&a, &b);
+    }
+
+    // many modifications, but only one entry per id.
+    assert!(eg.generation() - g > eg.touched.len());
+    assert!(eg.touched.len() <= eg.unionfind_len());
+    assert!(eg.touched_since(g).contains(&a.id));
+    assert!(eg.touched_since(eg.generation()).is_empty());
+}
@@ -0,0 +1,208 @@
+use crate::*;
+
+// Garbage collection.
+// The kept classes are copied into a fresh e-graph, which replaces `self`. This renumbers the e-classes,
+// hence both functions return the translation table from old Ids to new AppliedIds, as `merge` does.
+// Before copying, `self` drops its e-nodes and hashcons, so that the two e-graphs don't need to fit into memory at once.
+// This only happens once the copy is known to succeed: if some kept class has no finite term among its kept e-nodes, `self` stays unchanged.
+//
+// Open scopes of `push` are kept, and can still be popped.
+// With explanations enabled, the copied equations are justified by "prune", their original proofs are dropped.
+impl<L: Language> EGraph<L> {
+    // Removes all e-classes that are not reachable from `roots`.
+    pub fn prune(&mut self, roots: &[AppliedId]) -> Result<HashMap<Id, AppliedId>, NoFiniteTerm> {
+        let nodes = self.class_nodes();
+        self.keep(roots, nodes)
+    }
+
+    // Like `prune`, but additionally drops all e-nodes that are more expensive than the cheapest e-node of their class.
+    // If the cheapest e-nodes of some classes only form cycles (eg. for costs that don't grow with the term), these classes keep all their e-nodes.
+    // So do the classes that the extractor didn't reach, as the e-graph was cancelled.
+    pub fn prune_dominated<CF: CostFunction<L>>(&mut self, roots: &[AppliedId], cf: CF) -> Result<HashMap<Id, AppliedId>, NoFiniteTerm> {
+        let ex = Extractor::new_with(self, cf);
+        let cost = |j: Id| ex.map.get(&self.find_id(j)).map(|x| x.1.clone());
+
+        let all = self.class_nodes();
+        let mut nodes = all.clone();
+        for (c, v) in nodes.iter_mut() {
+            let Some(best) = cost(*c) else { continue };
+            v.retain(|x| {
+                if x.ids().into_iter().any(|j| cost(j).is_none()) { return true; }
+                ex.cost_function().cost(x, |j| cost(j).unwrap()) == best
+            });
+        }
+        if let Err(NoFiniteTerm(cs)) = self.term_order(&nodes) {
+            for c in cs {
+                nodes.insert(c, all[&c].clone());
+            }
+        }
+        self.keep(roots, nodes)
+    }
+
+    // keeps the e-nodes in `nodes` which are reachable from `roots` through other kept e-nodes.
+    fn keep(&mut self, roots: &[AppliedId], mut nodes: HashMap<Id, Vec<L>>) -> Result<HashMap<Id, AppliedId>, NoFiniteTerm> {
+        let mut reachable = HashSet::default();
+        let mut stack: Vec<Id> = roots.iter().map(|r| self.find_id(r.id)).collect();
+        while let Some(c) = stack.pop() {
+            if !reachable.insert(c) { continue; }
+            for x in &nodes[&c] {
+                stack.extend(x.ids().into_iter().map(|j| self.find_id(j)));
+            }
+        }
+        nodes.retain(|c, _| reachable.contains(c));
+        let order = self.term_order(&nodes)?;
+
+        // the innermost scope can't log the replacement of the whole e-graph.
+        self.detach_scope();
+
+        // `import` only needs the unionfind and the slots, groups and syn_enodes of the classes.
+        self.hashcons = HashMap::default();
+        self.syn_hashcons = HashMap::default();
+        self.pending = IndexSet::default();
+        self.touched = HashMap::default();
+        for c in self.classes.values_mut() {
+            c.nodes = HashMap::default();
+            c.usages = HashSet::default();
+        }
+
+        let mut out = EGraph::new();
+        out.check_level = self.check_level;
+        out.slot_allocator = self.slot_allocator.clone();
+
+        // The registry is kept, so that snapshots of `self` can still be restored.
+        // Its entries refer to the old Ids, they are only kept by these snapshots.
+        out.proof_registry = std::mem::take(&mut self.proof_registry);
+        out.proof_registry.set_entries(IndexMap::default());
+
+        let table = out.import(self, &nodes, &order, "prune");
+
+        // only set afterwards, so that the copy is complete even if the e-graph is cancelled.
+        out.budget = self.budget;
+        out.cancel_token = self.cancel_token.clone();
+        out.deadline = self.deadline;
+        out.epoch = self.epoch + 1;
+        out.scopes = std::mem::take(&mut self.scopes);
+        out.hooks = std::mem::take(&mut self.hooks);
+        *self = out;
+        self.emit(|| Event::Pruned { table: table.clone() });
+
+        if self.check_level >= CheckLevel::PerRebuild { self.check(); }
+
+        Ok(table)
+    }
+}
+
+#[test]
+fn prune_unreachable() {
+    let p = |s| RecExpr::parse(s).unwrap();
+    let mut eg = EGraph::<RiseENode>::new();
+    let j = eg.add_expr(p("(app (lam s2 (var s2)) sym_z)"));
+    let i = eg.add_expr(p("(lam s0 (app (var s0) (app sym_g sym_x)))"));
+
+    // the inner class has a cheaper e-node, which makes (app sym_g sym_x) dominated.
+    let k = eg.add_expr(p("(app sym_g sym_x)"));
+    let y = eg.add_expr(p("sym_y"));
+    eg.union(&k, &y);
+    let cost = Extractor::<_, AstSize>::new(&eg).cost(&i, &eg);
+    let mut ex = IncrementalExtractor::new(&eg, AstSize);
+    let n = eg.ids().len();
+
+    eg.push();
+    let table = eg.prune(&[i.clone()]).unwrap();
+    eg.check();
+    assert!(eg.ids().len() < n);
+
+    let i2 = translate_applied_id(&i, &table);
+    assert_eq!(Extractor::<_, AstSize>::new(&eg).cost(&i2, &eg), cost);
+    assert!(!table.contains_key(&j.id));
+
+    // the incremental extractor notices the new e-graph.
+    fn size(re: &RecExpr<RiseENode>) -> u64 { 1 + re.children.iter().map(size).sum::<u64>() }
+    ex.update(&eg);
+    assert_eq!(size(&ex.extract(i2.clone(), &eg).unwrap()), cost);
+
+    // the pruned e-graph can still be extended.
+    let f = eg.add_expr(p("(lam s1 (app (var s1) sym_y))"));
+    assert!(eg.eq(&f, &i2));
+    let g = eg.add_expr(p("(lam s1 sym_f)"));
+    eg.union(&f, &g);
+    eg.check();
+
+    eg.pop();
+    eg.check();
+    assert_eq!(eg.ids().len(), n);
+
+    let m = eg.total_number_of_nodes();
+    let table = eg.prune_dominated(&[i.clone()], AstSize).unwrap();
+    eg.check();
+    assert!(eg.total_number_of_nodes() < m);
+    let i3 = translate_applied_id(&i, &table);
+    assert_eq!(Extractor::<_, AstSize>::new(&eg).cost(&i3, &eg), cost);
+    for c in eg.ids() {
+        assert!(!eg.enodes(c).is_empty());
+    }
+}
+
+#[test]
+fn prune_dominated_cycle() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("sym_x").unwrap());
+    let f = eg.add_expr(RecExpr::parse("sym_f").unwrap());
+    let j = eg.add(RiseENode::App(f, i.clone()));
+    eg.union(&i, &j);
+
+    // (app sym_f <itself>) is cheaper than its class, but the class still needs its finite term.
+    let table = eg.prune_dominated(&[i.clone()], Shrinking).unwrap();
+    eg.check();
+    let i = translate_applied_id(&i, &table);
+    assert_eq!(extract::<_, AstSize>(i, &eg).to_string(), "sym_x");
+}
+
+#[test]
+fn restore_after_prune() {
+    let mut eg = EGraph::<RiseENode>::new();
+    eg.add_expr(RecExpr::parse("sym_y").unwrap());
+    let i = eg.add_expr(RecExpr::parse("(lam s0 (app (var s0) sym_x))").unwrap());
+    let n = eg.ids().len();
+
+    let s = eg.snapshot();
+    eg.prune(&[i]).unwrap();
+    assert!(eg.ids().len() < n);
+    eg.restore(&s);
+    eg.check();
+    assert_eq!(eg.ids().len(), n);
+}
+
+#[test]
+fn prune_without_finite_term() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let x = eg.add_expr(RecExpr::parse("sym_x").unwrap());
+    let y = eg.add(RiseENode::App(x.clone(), x.clone()));
+    eg.union(&x, &y);
+    let saved = eg.save(true);
+
+    // only the cyclic e-node is kept, so the e-graph stays as it is.
+    let c = eg.find_id(x.id);
+    let mut nodes = eg.class_nodes();
+    nodes.get_mut(&c).unwrap().retain(|n| matches!(n, RiseENode::App(..)));
+    assert_eq!(eg.keep(&[x], nodes), Err(NoFiniteTerm(vec![c])));
+    assert_eq!(eg.save(true), saved);
+}
+
+#[test]
+fn prune_dominated_when_cancelled() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app sym_f sym_x)").unwrap());
+    let j = eg.add_expr(RecExpr::parse("sym_y").unwrap());
+    eg.union(&i, &j);
+    eg.cancel_token().cancel();
+
+    // the extractor doesn't reach any class, so all e-nodes are kept, and the copy is still complete.
+    let n = eg.total_number_of_nodes();
+    let table = eg.prune_dominated(&[i.clone()], AstSize).unwrap();
+    eg.check();
+    assert_eq!(eg.total_number_of_nodes(), n);
+    assert!(eg.is_cancelled());
+    let i = translate_applied_id(&i, &table);
+    assert_eq!(eg.enodes(i.id).len(), 2);
+}
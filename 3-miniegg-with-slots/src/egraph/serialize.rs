@@ -0,0 +1,453 @@
+use crate::*;
+
+use std::fmt::Write;
+
+// A line-based text format for whole e-graphs.
+// It allows to checkpoint long saturation runs, and to share e-graphs in bug reports.
+//
+// slotted-egraph 1
+// allocator <counter>                  -- the number of slots generated by the SlotAllocator, so that fresh slots don't collide with the loaded ones.
+// proof <k> <l> <r> <kind>              -- the k-th ProvenEq, kind is one of:
+//                                          refl | symm <k> | trans <k> <k> | cong <k>* | explicit | explicit =<justification>
+// registry <k>                          -- an entry of the proof registry.
+// uf <i> <applied-id> <k>               -- the unionfind entry of id<i>, in order.
+// class <i> <syn-enode> <k> <slot>*     -- an e-class with its syn_enode, redundancy proof and slots.
+// node <shape> <bijection> <applied-id> -- an e-node of the last class.
+// usage <shape>                         -- a usage of the last class.
+// perm <perm> <k>                       -- a generator of the symmetry group of the last class.
+// hashcons <shape> <i>
+// syn_hashcons <weak-shape> <applied-id>
+// pending <shape>
+//
+// Slot maps are written as [s0=s1,s2=s3], applied ids as id4[s0=s1], and e-nodes as (op child*), where each child is a slot or an applied id.
+// The k-th slot of the SlotAllocator of the e-graph is written as f<k>, so that the output doesn't depend on its namespace.
+// When loading, these slots are translated to the namespace of the new e-graph.
+// Proofs are always written before they are referenced.
+// If proofs are excluded, every ProvenEq is written as `explicit`, so only its equation is kept.
+// Justifications escape backslashes and line breaks as \\, \n and \r, so that each proof stays on a single line.
+
+impl<L: Language> EGraph<L> {
+    pub fn save(&self, with_proofs: bool) -> String {
+        let a = &self.slot_allocator;
+        let mut w = ProofWriter { with_proofs, out: String::new(), ids: HashMap::default(), a };
+        let mut body = String::new();
+
+        // The registry comes first, so that proofs generated while loading (eg. for the group identities) don't shadow the stored ones.
+        if with_proofs {
+            let mut reg = self.proof_registry.proofs();
+            reg.sort_by_key(|p| format!("{:?}", p.equ()));
+            for p in reg {
+                let k = w.write(&p);
+                writeln!(body, "registry {k}").unwrap();
+            }
+        }
+
+        let uf: Vec<(AppliedId, ProvenEq)> = self.unionfind.lock().unwrap().clone();
+        for (i, (app, prf)) in uf.iter().enumerate() {
+            let k = w.write(prf);
+            writeln!(body, "uf {i} {} {k}", app_id_str(app, a)).unwrap();
+        }
+
+        let mut ids: Vec<Id> = self.classes.keys().copied().collect();
+        ids.sort();
+        for i in ids {
+            let c = &self.classes[&i];
+            let k = w.write(&c.redundancy_proof);
+            write!(body, "class {} {} {k}", i.0, enode_str(&c.syn_enode, a)).unwrap();
+            for s in sorted(c.slots.iter().map(|s| slot_str(*s, a))) {
+                write!(body, " {s}").unwrap();
+            }
+            writeln!(body).unwrap();
+
+            let nodes = c.nodes.iter().map(|(sh, (bij, app))| format!("{} {} {}", enode_str(sh, a), slotmap_str(bij, a), app_id_str(app, a)));
+            for x in sorted(nodes) {
+                writeln!(body, "node {x}").unwrap();
+            }
+            for x in sorted(c.usages.iter().map(|x| enode_str(x, a))) {
+                writeln!(body, "usage {x}").unwrap();
+            }
+
+            let mut perms: Vec<ProvenPerm> = c.group.generators().into_iter().collect();
+            perms.sort_by_key(|p| p.0.clone());
+            for ProvenPerm(p, prf, _) in &perms {
+                let k = w.write(prf);
+                writeln!(body, "perm {} {k}", slotmap_str(p, a)).unwrap();
+            }
+        }
+
+        for x in sorted(self.hashcons.iter().map(|(sh, i)| format!("{} {}", enode_str(sh, a), i.0))) {
+            writeln!(body, "hashcons {x}").unwrap();
+        }
+        for x in sorted(self.syn_hashcons.iter().map(|(sh, app)| format!("{} {}", enode_str(sh, a), app_id_str(app, a)))) {
+            writeln!(body, "syn_hashcons {x}").unwrap();
+        }
+        for x in sorted(self.pending.iter().map(|x| enode_str(x, a))) {
+            writeln!(body, "pending {x}").unwrap();
+        }
+
+        format!("slotted-egraph 1\nallocator {}\n{}{}", a.counter(), w.out, body)
+    }
+
+    // Returns None, if `s` is not a valid output of `save`.
+    // This includes files that parse, but describe an e-graph violating its invariants (eg. after editing them by hand), see `violations`.
+    pub fn load(s: &str) -> Option<Self> {
+        let mut lines = s.lines();
+        if lines.next()?.trim() != "slotted-egraph 1" { return None; }
+
+        let mut eg = EGraph::new();
+        let a = eg.slot_allocator.clone();
+        let reg = eg.proof_registry.clone();
+        let mut proofs: Vec<ProvenEq> = Vec::new();
+        let mut uf: Vec<(AppliedId, ProvenEq)> = Vec::new();
+        let mut current: Option<Id> = None;
+        let mut generators: HashMap<Id, HashSet<ProvenPerm>> = HashMap::default();
+
+        for line in lines {
+            // trailing whitespace might belong to a justification, so proof lines are only trimmed at the front.
+            let line = line.trim_start();
+
+            // the justification of an explicit proof may contain anything, hence proofs are split manually.
+            if let Some(rest) = line.strip_prefix("proof ") {
+                let mut it = rest.splitn(4, ' ');
+                let k: usize = it.next()?.parse().ok()?;
+                if k != proofs.len() { return None; }
+                let l = parse_app_id(it.next()?, &a)?;
+                let r = parse_app_id(it.next()?, &a)?;
+                let proof = parse_proof(it.next()?, &proofs)?;
+                proofs.push(ProvenEqRaw::new_unchecked(Equation { l, r }, proof));
+                continue;
+            }
+
+            let line = line.trim_end();
+            if line.is_empty() { continue; }
+
+            let toks = tokens(line);
+            let prf = |k: &str| -> Option<ProvenEq> { proofs.get(k.parse::<usize>().ok()?).cloned() };
+            match &toks[..] {
+                ["uf", i, app, k] => {
+                    if i.parse::<usize>().ok()? != uf.len() { return None; }
+                    uf.push((parse_app_id(app, &a)?, prf(k)?));
+                },
+                ["class", i, syn, k, slots @ ..] => {
+                    let i = Id(i.parse().ok()?);
+                    let syn_enode: L = parse_enode(syn, &a)?;
+                    let slots: HashSet<Slot> = slots.iter().map(|s| parse_slot(s, &a)).collect::<Option<_>>()?;
+                    let identity = ProvenPerm::identity(i, &slots, &syn_enode.slots(), reg.clone());
+                    let c = EClass {
+                        nodes: HashMap::default(),
+                        slots,
+                        usages: HashSet::default(),
+                        group: Group::identity(&identity),
+                        syn_enode,
+                        redundancy_proof: prf(k)?,
+                    };
+                    eg.classes.insert(i, c);
+                    current = Some(i);
+                },
+                ["node", sh, bij, app] => {
+                    let c = eg.classes.get_mut(&current?)?;
+                    c.nodes.insert(parse_enode(sh, &a)?, (parse_slotmap(bij, &a)?, parse_app_id(app, &a)?));
+                },
+                ["usage", sh] => {
+                    let c = eg.classes.get_mut(&current?)?;
+                    c.usages.insert(parse_enode(sh, &a)?);
+                },
+                ["perm", p, k] => {
+                    generators.entry(current?).or_default().insert(ProvenPerm(parse_slotmap(p, &a)?, prf(k)?, reg.clone()));
+                },
+                ["hashcons", sh, i] => {
+                    eg.hashcons.insert(parse_enode(sh, &a)?, Id(i.parse().ok()?));
+                },
+                ["syn_hashcons", sh, app] => {
+                    eg.syn_hashcons.insert(parse_enode(sh, &a)?, parse_app_id(app, &a)?);
+                },
+                ["allocator", k] => {
+                    a.set_counter(k.parse().ok()?);
+                },
+                ["pending", sh] => {
+                    eg.pending.insert(parse_enode(sh, &a)?);
+                },
+                ["registry", k] => {
+                    reg.insert(prf(k)?);
+                },
+                _ => return None,
+            }
+        }
+
+        eg.unionfind = std::sync::Mutex::new(uf);
+
+        for (i, gens) in generators {
+            eg.classes.get_mut(&i)?.group.add_set(gens);
+        }
+
+        // Consumers of `touched_since` can't know this e-graph yet, so every class counts as touched.
+        let mut ids: Vec<Id> = eg.classes.keys().copied().collect();
+        ids.sort();
+        for i in ids {
+            eg.touch(i);
+        }
+
+        if !eg.violations().is_empty() { return None; }
+
+        Some(eg)
+    }
+}
+
+#[test]
+fn save_load_roundtrip() {
+    let rules = rise_rules(SubstMethod::SmallStep);
+    let p = |s| RecExpr::parse(s).unwrap();
+    let mut eg = EGraph::<RiseENode>::new();
+
+    // a symmetric class and a redundant slot, so that groups and redundancy proofs are written.
+    let a = eg.add_expr(p("(app (var s1) (var s2))"));
+    let b = eg.add_expr(p("(app (var s2) (var s1))"));
+    eg.union(&a, &b);
+    let c = eg.add_expr(p("(app (var s3) sym_x)"));
+    let d = eg.add_expr(p("sym_y"));
+    eg.union(&c, &d);
+
+    // a beta-redex, so that saturation has something to do after loading.
+    let i = eg.add_expr(p("(app (lam s0 (app (var s0) sym_y)) (lam s4 (var s4)))"));
+
+    // `check` compresses the unionfind, which would otherwise happen during `load`.
+    eg.check();
+
+    for with_proofs in [true, false] {
+        let s = eg.save(with_proofs);
+        let mut eg2 = EGraph::<RiseENode>::load(&s).unwrap();
+        eg2.check();
+        assert_eq!(eg2.save(with_proofs), s);
+
+        // saturation can be resumed.
+        // The results can't be compared textually, as the rewrites generate fresh slot names.
+        let mut eg1 = EGraph::load(&eg.save(with_proofs)).unwrap();
+        for _ in 0..2 {
+            do_rewrites(&mut eg1, &rules);
+            do_rewrites(&mut eg2, &rules);
+        }
+        assert_eq!(eg1.ids().len(), eg2.ids().len());
+        assert_eq!(eg1.total_number_of_nodes(), eg2.total_number_of_nodes());
+        let cost = |eg: &EGraph<RiseENode>| Extractor::<_, AstSize>::new(eg).cost(&i, eg);
+        assert_eq!(cost(&eg1), cost(&eg2));
+    }
+
+    assert!(EGraph::<RiseENode>::load("slotted-egraph 1\nfoo").is_none());
+    assert!(EGraph::<RiseENode>::load("slotted-egraph 1\nproof 0 id0[] id0[] ").is_none());
+}
+
+#[test]
+fn load_rejects_tampered_class() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(lam s0 (app (var s0) (var s1)))").unwrap());
+    let s = eg.save(true);
+    assert!(EGraph::<RiseENode>::load(&s).is_some());
+
+    // dropping a slot of the class still parses, but its e-nodes and users no longer fit.
+    let class = s.lines().find(|l| l.starts_with(&format!("class {} ", i.id.0))).unwrap();
+    let (rest, slot) = class.rsplit_once(' ').unwrap();
+    assert!(slot.starts_with('f') || slot.starts_with('s'));
+    let tampered = s.replace(class, rest);
+    assert!(EGraph::<RiseENode>::load(&tampered).is_none());
+}
+
+#[test]
+fn save_load_justifications() {
+    // explanations might be disabled, so the proof is written directly.
+    let j = String::from("first line\nsecond line \\n, not a newline\r\n  indented ");
+    let eq = Equation { l: AppliedId::new(Id(0), SlotMap::new()), r: AppliedId::new(Id(1), SlotMap::new()) };
+    let p = ProvenEqRaw::new_unchecked(eq, Proof::Explicit(ExplicitProof(Some(j.clone()))));
+
+    let a = SlotAllocator::new();
+    let mut w = ProofWriter { with_proofs: true, out: String::new(), ids: HashMap::default(), a: &a };
+    w.write(&p);
+    assert_eq!(w.out.lines().count(), 1);
+
+    let kind = w.out.strip_prefix("proof 0 id0[] id1[] ").unwrap().strip_suffix('\n').unwrap();
+    assert_eq!(kind, "explicit =first line\\nsecond line \\\\n, not a newline\\r\\n  indented ");
+    assert!(matches!(parse_proof(kind, &[]), Some(Proof::Explicit(ExplicitProof(Some(x)))) if x == j));
+    assert!(parse_proof("explicit =dangling \\", &[]).is_none());
+}
+
+struct ProofWriter<'a> {
+    with_proofs: bool,
+    out: String,
+
+    // the number of each ProvenEq that was already written.
+    ids: HashMap<*const ProvenEqRaw, usize>,
+
+    // the allocator of the e-graph, whose slots are written relative to its namespace.
+    a: &'a SlotAllocator,
+}
+
+impl ProofWriter<'_> {
+    // writes `p` (and its subproofs) if necessary, and returns its number.
+    fn write(&mut self, p: &ProvenEq) -> usize {
+        let mut stack: Vec<&ProvenEq> = vec![p];
+        while let Some(x) = stack.last().copied() {
+            if self.ids.contains_key(&Arc::as_ptr(x)) {
+                stack.pop();
+                continue;
+            }
+
+            let subs = if self.with_proofs { x.subproofs() } else { Vec::new() };
+            let missing: Vec<&ProvenEq> = subs.iter().copied().filter(|s| !self.ids.contains_key(&Arc::as_ptr(s))).collect();
+            if !missing.is_empty() {
+                stack.extend(missing);
+                continue;
+            }
+
+            let sub_ids: Vec<String> = subs.iter().map(|s| self.ids[&Arc::as_ptr(s)].to_string()).collect();
+            let kind = match x.proof() {
+                _ if !self.with_proofs => String::from("explicit"),
+                Proof::Explicit(ExplicitProof(None)) => String::from("explicit"),
+                Proof::Explicit(ExplicitProof(Some(j))) => format!("explicit ={}", escape(j)),
+                Proof::Reflexivity(_) => String::from("refl"),
+                Proof::Symmetry(_) => format!("symm {}", sub_ids[0]),
+                Proof::Transitivity(_) => format!("trans {} {}", sub_ids[0], sub_ids[1]),
+                Proof::Congruence(_) => format!("cong {}", sub_ids.join(" ")).trim().to_string(),
+            };
+
+            let k = self.ids.len();
+            writeln!(self.out, "proof {k} {} {} {kind}", app_id_str(&x.l, self.a), app_id_str(&x.r, self.a)).unwrap();
+            self.ids.insert(Arc::as_ptr(x), k);
+            stack.pop();
+        }
+        self.ids[&Arc::as_ptr(p)]
+    }
+}
+
+fn parse_proof(s: &str, proofs: &[ProvenEq]) -> Option<Proof> {
+    if let Some(j) = s.strip_prefix("explicit =") {
+        return Some(Proof::Explicit(ExplicitProof(Some(unescape(j)?))));
+    }
+
+    let toks: Vec<&str> = s.split_whitespace().collect();
+    let (kind, subs) = toks.split_first()?;
+    let subs: Vec<ProvenEq> = subs.iter().map(|k| proofs.get(k.parse::<usize>().ok()?).cloned()).collect::<Option<_>>()?;
+    match (*kind, &subs[..]) {
+        ("explicit", []) => Some(Proof::Explicit(ExplicitProof(None))),
+        ("refl", []) => Some(Proof::Reflexivity(ReflexivityProof)),
+        ("symm", [a]) => Some(Proof::Symmetry(SymmetryProof(a.clone()))),
+        ("trans", [a, b]) => Some(Proof::Transitivity(TransitivityProof(a.clone(), b.clone()))),
+        ("cong", _) => Some(Proof::Congruence(CongruenceProof(subs))),
+        _ => None,
+    }
+}
+
+// justifications have to fit into a single line.
+fn escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('\n', "\\n").replace('\r', "\\r")
+}
+
+fn unescape(s: &str) -> Option<String> {
+    let mut out = String::new();
+    let mut it = s.chars();
+    while let Some(c) = it.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match it.next()? {
+            '\\' => out.push('\\'),
+            'n' => out.push('\n'),
+            'r' => out.push('\r'),
+            _ => return None,
+        }
+    }
+    Some(out)
+}
+
+fn sorted(it: impl Iterator<Item=String>) -> Vec<String> {
+    let mut v: Vec<String> = it.collect();
+    v.sort();
+    v
+}
+
+fn slot_str(s: Slot, a: &SlotAllocator) -> String {
+    match a.index_of(s) {
+        Some(k) => format!("f{k}"),
+        None => s.to_string(),
+    }
+}
+
+fn slotmap_str(m: &SlotMap, a: &SlotAllocator) -> String {
+    let entries: Vec<String> = m.iter().map(|(x, y)| format!("{}={}", slot_str(x, a), slot_str(y, a))).collect();
+    format!("[{}]", entries.join(","))
+}
+
+fn app_id_str(app: &AppliedId, a: &SlotAllocator) -> String {
+    format!("id{}{}", app.id.0, slotmap_str(&app.m, a))
+}
+
+fn enode_str<L: Language>(l: &L, a: &SlotAllocator) -> String {
+    let (op, children) = l.to_op();
+    let mut out = format!("({op}");
+    for c in children {
+        match c {
+            Child::Slot(s) => write!(out, " {}", slot_str(s, a)).unwrap(),
+            Child::AppliedId(app) => write!(out, " {}", app_id_str(&app, a)).unwrap(),
+        }
+    }
+    out.push(')');
+    out
+}
+
+fn parse_slot(s: &str, a: &SlotAllocator) -> Option<Slot> {
+    if let Some(k) = s.strip_prefix('f') {
+        let k: i64 = k.parse().ok()?;
+        return (k > 0).then(|| a.nth(k));
+    }
+    Some(Slot::new_unchecked(s.strip_prefix('s')?.parse().ok()?))
+}
+
+fn parse_slotmap(s: &str, a: &SlotAllocator) -> Option<SlotMap> {
+    let s = s.strip_prefix('[')?.strip_suffix(']')?;
+    let mut m = SlotMap::new();
+    for entry in s.split(',').filter(|x| !x.is_empty()) {
+        let (x, y) = entry.split_once('=')?;
+        m.insert(parse_slot(x, a)?, parse_slot(y, a)?);
+    }
+    Some(m)
+}
+
+fn parse_app_id(s: &str, a: &SlotAllocator) -> Option<AppliedId> {
+    let s = s.strip_prefix("id")?;
+    let (i, m) = s.split_at(s.find('[')?);
+    Some(AppliedId::new(Id(i.parse().ok()?), parse_slotmap(m, a)?))
+}
+
+fn parse_enode<L: Language>(s: &str, a: &SlotAllocator) -> Option<L> {
+    let s = s.strip_prefix('(')?.strip_suffix(')')?;
+    let mut it = s.split_whitespace();
+    let op = it.next()?;
+    let children = it.map(|c| {
+        if c.starts_with("id") {
+            parse_app_id(c, a).map(Child::AppliedId)
+        } else {
+            parse_slot(c, a).map(Child::Slot)
+        }
+    }).collect::<Option<_>>()?;
+    L::from_op(op, children)
+}
+
+// splits a line at whitespace, but keeps parenthesized e-nodes together.
+fn tokens(line: &str) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut start = None;
+    for (i, c) in line.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => depth -= 1,
+            _ => {},
+        }
+        if c.is_whitespace() && depth == 0 {
+            if let Some(st) = start.take() { out.push(&line[st..i]); }
+        } else if start.is_none() {
+            start = Some(i);
+        }
+    }
+    if let Some(st) = start { out.push(&line[st..]); }
+    out
+}
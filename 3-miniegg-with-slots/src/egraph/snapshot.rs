@@ -0,0 +1,491 @@
+use crate::*;
+
+use super::memory::Sizer;
+use std::mem::size_of;
+
+// A copy of the state of an e-graph, that can be restored later on.
+// Taking a snapshot copies the whole e-graph, so it is linear in its size.
+// For backtracking, `push` and `pop` are cheaper, as they only record what changes in between.
+//
+// A snapshot can only be restored into the e-graph it was taken from.
+#[derive(Clone, Debug)]
+pub struct Snapshot<L: Language> {
+    unionfind: Vec<(AppliedId, ProvenEq)>,
+    classes: HashMap<Id, EClass<L>>,
+    hashcons: HashMap<L, Id>,
+    syn_hashcons: HashMap<L, AppliedId>,
+    pending: IndexSet<L>,
+    touched: HashMap<Id, usize>,
+    generation: usize,
+
+    // The registry is shared with the ProvenPerms of the e-graph, so we store its contents instead of a copy.
+    // The registry itself identifies the e-graph.
+    proof_registry: ProofRegistry,
+    proofs: IndexMap<Equation, ProvenEq>,
+}
+
+// The values overwritten since a `push`, so that `pop` can put them back.
+// Each entry is recorded on its first change within the scope, so the log is proportional to the changes, not to the e-graph.
+// Entries that didn't exist at the time of the `push` are recorded as `None`.
+#[derive(Debug)]
+pub(in crate::egraph) struct UndoLog<L: Language> {
+    // the unionfind only grows, so its new entries are truncated.
+    unionfind_len: usize,
+    unionfind: HashMap<Id, (AppliedId, ProvenEq)>,
+    classes: HashMap<Id, Option<EClass<L>>>,
+    hashcons: HashMap<L, Option<Id>>,
+    syn_hashcons: HashMap<L, Option<AppliedId>>,
+    touched: HashMap<Id, Option<usize>>,
+    generation: usize,
+    proofs_len: usize,
+
+    // `pending` is empty after each `rebuild`, so it's simply copied.
+    pending: IndexSet<L>,
+}
+
+#[derive(Debug)]
+pub(in crate::egraph) enum Scope<L: Language> {
+    Log(UndoLog<L>),
+
+    // Operations that replace the whole e-graph (like `prune` or `restore`) can't be logged,
+    // so they first turn the innermost scope into a full copy.
+    Copy(Snapshot<L>),
+}
+
+impl<L: Language> EGraph<L> {
+    pub fn snapshot(&self) -> Snapshot<L> {
+        Snapshot {
+            unionfind: self.unionfind.lock().unwrap().clone(),
+            classes: self.classes.clone(),
+            hashcons: self.hashcons.clone(),
+            syn_hashcons: self.syn_hashcons.clone(),
+            pending: self.pending.clone(),
+            touched: self.touched.clone(),
+            generation: self.generation,
+            proof_registry: self.proof_registry.clone(),
+            proofs: self.proof_registry.entries(),
+        }
+    }
+
+    // Rolls the e-graph back to the state of `s`.
+    // The scopes opened by `push` are not affected.
+    pub fn restore(&mut self, s: &Snapshot<L>) {
+        assert!(self.proof_registry.ptr_eq(&s.proof_registry), "EGraph::restore: snapshot of a different e-graph");
+        self.detach_scope();
+        self.restore_impl(s);
+    }
+
+    fn restore_impl(&mut self, s: &Snapshot<L>) {
+        *self.unionfind.lock().unwrap() = s.unionfind.clone();
+        self.classes = s.classes.clone();
+        self.hashcons = s.hashcons.clone();
+        self.syn_hashcons = s.syn_hashcons.clone();
+        self.pending = s.pending.clone();
+        self.touched = s.touched.clone();
+        self.generation = s.generation;
+        self.proof_registry.set_entries(s.proofs.clone());
+
+        // e-nodes might have disappeared, so consumers of `touched_since` need to start over.
+        self.epoch += 1;
+        self.emit(|| Event::RolledBack);
+
+        if self.check_level >= CheckLevel::PerRebuild { self.check(); }
+    }
+
+    // Opens a new scope, which can be rolled back using `pop`.
+    // This is cheap, the scope records the changes made until the matching `pop` or `commit`.
+    pub fn push(&mut self) {
+        let log = UndoLog {
+            unionfind_len: self.unionfind_len(),
+            unionfind: HashMap::default(),
+            classes: HashMap::default(),
+            hashcons: HashMap::default(),
+            syn_hashcons: HashMap::default(),
+            touched: HashMap::default(),
+            generation: self.generation,
+            proofs_len: self.proof_registry.len(),
+            pending: self.pending.clone(),
+        };
+        self.scopes.push(Scope::Log(log));
+    }
+
+    // Rolls the e-graph back to the state of the matching `push`.
+    pub fn pop(&mut self) {
+        match self.scopes.pop().expect("EGraph::pop without matching push") {
+            Scope::Copy(s) => self.restore_impl(&s),
+            Scope::Log(log) => {
+                let proofs_len = log.undo(Tables {
+                    unionfind: &mut self.unionfind.lock().unwrap(),
+                    classes: &mut self.classes,
+                    hashcons: &mut self.hashcons,
+                    syn_hashcons: &mut self.syn_hashcons,
+                    pending: &mut self.pending,
+                    touched: &mut self.touched,
+                    generation: &mut self.generation,
+                });
+                self.proof_registry.truncate(proofs_len);
+
+                self.epoch += 1;
+                self.emit(|| Event::RolledBack);
+                if self.check_level >= CheckLevel::PerRebuild { self.check(); }
+            },
+        }
+    }
+
+    // Closes the innermost scope while keeping its changes.
+    pub fn commit(&mut self) {
+        let inner = self.scopes.pop().expect("EGraph::commit without matching push");
+        let Some(outer) = self.scopes.pop() else { return };
+        let outer = match (outer, inner) {
+            (Scope::Copy(s), _) => Scope::Copy(s),
+            (Scope::Log(mut outer), Scope::Log(inner)) => {
+                // the outer scope keeps its older values.
+                for (i, x) in inner.unionfind {
+                    if i.0 < outer.unionfind_len {
+                        outer.unionfind.entry(i).or_insert(x);
+                    }
+                }
+                for (k, v) in inner.classes { outer.classes.entry(k).or_insert(v); }
+                for (k, v) in inner.hashcons { outer.hashcons.entry(k).or_insert(v); }
+                for (k, v) in inner.syn_hashcons { outer.syn_hashcons.entry(k).or_insert(v); }
+                for (k, v) in inner.touched { outer.touched.entry(k).or_insert(v); }
+                Scope::Log(outer)
+            },
+            (Scope::Log(outer), Scope::Copy(mut s)) => {
+                s.undo(outer);
+                Scope::Copy(s)
+            },
+        };
+        self.scopes.push(outer);
+    }
+
+    // The number of currently open scopes.
+    pub fn scope_depth(&self) -> usize {
+        self.scopes.len()
+    }
+
+    // Turns the innermost scope into a full copy, so that the e-graph can be replaced as a whole.
+    pub(in crate::egraph) fn detach_scope(&mut self) {
+        if let Some(Scope::Log(_)) = self.scopes.last() {
+            let Some(Scope::Log(log)) = self.scopes.pop() else { unreachable!() };
+            let mut s = self.snapshot();
+            s.undo(log);
+            self.scopes.push(Scope::Copy(s));
+        }
+    }
+
+    // The length of the unionfind at the innermost `push`, if it is logged.
+    // Entries below it must not change without being logged, see `unionfind_get_impl`.
+    pub(in crate::egraph) fn logged_unionfind_len(&self) -> Option<usize> {
+        match self.scopes.last() {
+            Some(Scope::Log(log)) => Some(log.unionfind_len),
+            _ => None,
+        }
+    }
+
+    fn undo_log(&mut self) -> Option<&mut UndoLog<L>> {
+        match self.scopes.last_mut() {
+            Some(Scope::Log(log)) => Some(log),
+            _ => None,
+        }
+    }
+
+    // All modifications of the e-graph tables go through the following functions, so that the innermost scope can log them.
+
+    pub(in crate::egraph) fn class_mut(&mut self, i: Id) -> &mut EClass<L> {
+        if let Some(Scope::Log(log)) = self.scopes.last_mut() {
+            log.classes.entry(i).or_insert_with(|| self.classes.get(&i).cloned());
+        }
+        self.classes.get_mut(&i).unwrap()
+    }
+
+    pub(in crate::egraph) fn insert_class(&mut self, i: Id, c: EClass<L>) {
+        if let Some(Scope::Log(log)) = self.scopes.last_mut() {
+            log.classes.entry(i).or_insert_with(|| self.classes.get(&i).cloned());
+        }
+        self.classes.insert(i, c);
+    }
+
+    pub(in crate::egraph) fn hashcons_insert(&mut self, sh: L, i: Id) -> Option<Id> {
+        if let Some(Scope::Log(log)) = self.scopes.last_mut() {
+            log.hashcons.entry(sh.clone()).or_insert_with(|| self.hashcons.get(&sh).copied());
+        }
+        self.hashcons.insert(sh, i)
+    }
+
+    pub(in crate::egraph) fn hashcons_remove(&mut self, sh: &L) -> Option<Id> {
+        if let Some(Scope::Log(log)) = self.scopes.last_mut() {
+            log.hashcons.entry(sh.clone()).or_insert_with(|| self.hashcons.get(sh).copied());
+        }
+        self.hashcons.remove(sh)
+    }
+
+    pub(in crate::egraph) fn syn_hashcons_insert(&mut self, sh: L, a: AppliedId) {
+        if let Some(Scope::Log(log)) = self.scopes.last_mut() {
+            log.syn_hashcons.entry(sh.clone()).or_insert_with(|| self.syn_hashcons.get(&sh).cloned());
+        }
+        self.syn_hashcons.insert(sh, a);
+    }
+
+    pub(in crate::egraph) fn touch(&mut self, i: Id) {
+        if let Some(Scope::Log(log)) = self.scopes.last_mut() {
+            log.touched.entry(i).or_insert_with(|| self.touched.get(&i).copied());
+        }
+        self.touched.insert(i, self.generation);
+        self.generation += 1;
+    }
+
+    pub(in crate::egraph) fn log_unionfind_entry(&mut self, i: Id) {
+        let Some(n) = self.logged_unionfind_len() else { return };
+        if i.0 >= n { return; }
+
+        let old = self.unionfind.lock().unwrap()[i.0].clone();
+        self.undo_log().unwrap().unionfind.entry(i).or_insert(old);
+    }
+}
+
+impl<L: Language> Snapshot<L> {
+    // the number of Ids at the time of the snapshot, including the dead ones.
+    pub fn number_of_ids(&self) -> usize {
+        self.unionfind.len()
+    }
+
+    // whether `i` was the leader of its e-class at the time of the snapshot.
+    pub fn is_alive(&self, i: Id) -> bool {
+        self.unionfind.get(i.0).is_some_and(|(a, _)| a.id == i)
+    }
+
+    // the slots that the e-class `i` had at the time of the snapshot.
+    pub fn slots(&self, i: Id) -> &HashSet<Slot> {
+        &self.classes[&i].slots
+    }
+
+    // the shapes of all e-nodes at the time of the snapshot.
+    pub fn shapes(&self) -> impl Iterator<Item=&L> {
+        self.hashcons.keys()
+    }
+
+    // rolls the snapshot back to the state at the `push` of `log`.
+    fn undo(&mut self, log: UndoLog<L>) {
+        let proofs_len = log.undo(Tables {
+            unionfind: &mut self.unionfind,
+            classes: &mut self.classes,
+            hashcons: &mut self.hashcons,
+            syn_hashcons: &mut self.syn_hashcons,
+            pending: &mut self.pending,
+            touched: &mut self.touched,
+            generation: &mut self.generation,
+        });
+        self.proofs.truncate(proofs_len);
+    }
+}
+
+// The tables of an e-graph or a snapshot.
+struct Tables<'a, L: Language> {
+    unionfind: &'a mut Vec<(AppliedId, ProvenEq)>,
+    classes: &'a mut HashMap<Id, EClass<L>>,
+    hashcons: &'a mut HashMap<L, Id>,
+    syn_hashcons: &'a mut HashMap<L, AppliedId>,
+    pending: &'a mut IndexSet<L>,
+    touched: &'a mut HashMap<Id, usize>,
+    generation: &'a mut usize,
+}
+
+impl<L: Language> UndoLog<L> {
+    // puts the recorded values back, and returns the length the proof registry had.
+    fn undo(self, t: Tables<'_, L>) -> usize {
+        t.unionfind.truncate(self.unionfind_len);
+        for (i, x) in self.unionfind {
+            t.unionfind[i.0] = x;
+        }
+        put_back(t.classes, self.classes);
+        put_back(t.hashcons, self.hashcons);
+        put_back(t.syn_hashcons, self.syn_hashcons);
+        put_back(t.touched, self.touched);
+        *t.pending = self.pending;
+        *t.generation = self.generation;
+        self.proofs_len
+    }
+}
+
+impl<L: Language> Scope<L> {
+    // the memory of what the scope recorded, see `EGraph::memory_report`.
+    pub(in crate::egraph) fn heap_size(&self, sz: &mut Sizer) -> usize {
+        match self {
+            Scope::Log(log) => log.heap_size(sz),
+            Scope::Copy(s) => s.heap_size(sz),
+        }
+    }
+}
+
+impl<L: Language> UndoLog<L> {
+    fn heap_size(&self, sz: &mut Sizer) -> usize {
+        let mut x = hash_table_size::<Id, (AppliedId, ProvenEq)>(self.unionfind.capacity())
+                  + hash_table_size::<Id, Option<EClass<L>>>(self.classes.capacity())
+                  + hash_table_size::<L, Option<Id>>(self.hashcons.capacity())
+                  + hash_table_size::<L, Option<AppliedId>>(self.syn_hashcons.capacity())
+                  + hash_table_size::<Id, Option<usize>>(self.touched.capacity())
+                  + sz.pending(&self.pending);
+        for (a, _) in self.unionfind.values() {
+            x += sz.slotmap(&a.m);
+        }
+        for c in self.classes.values().flatten() {
+            let (class, nodes, usages, groups) = sz.class(c);
+            x += class + nodes + usages + groups;
+        }
+        for sh in self.hashcons.keys() {
+            x += sz.enode(sh);
+        }
+        for (sh, a) in &self.syn_hashcons {
+            x += sz.enode(sh) + a.as_ref().map(|a| sz.slotmap(&a.m)).unwrap_or(0);
+        }
+        x
+    }
+}
+
+impl<L: Language> Snapshot<L> {
+    fn heap_size(&self, sz: &mut Sizer) -> usize {
+        let mut x = self.unionfind.capacity() * size_of::<(AppliedId, ProvenEq)>()
+                  + hash_table_size::<Id, EClass<L>>(self.classes.capacity())
+                  + hash_table_size::<L, Id>(self.hashcons.capacity())
+                  + hash_table_size::<L, AppliedId>(self.syn_hashcons.capacity())
+                  + hash_table_size::<Id, usize>(self.touched.capacity())
+                  + hash_table_size::<Equation, ProvenEq>(self.proofs.capacity())
+                  + sz.pending(&self.pending);
+        for (a, _) in &self.unionfind {
+            x += sz.slotmap(&a.m);
+        }
+        for c in self.classes.values() {
+            let (class, nodes, usages, groups) = sz.class(c);
+            x += class + nodes + usages + groups;
+        }
+        for sh in self.hashcons.keys() {
+            x += sz.enode(sh);
+        }
+        for (sh, a) in &self.syn_hashcons {
+            x += sz.enode(sh) + sz.slotmap(&a.m);
+        }
+        for eq in self.proofs.keys() {
+            x += sz.slotmap(&eq.l.m) + sz.slotmap(&eq.r.m);
+        }
+        x
+    }
+}
+
+fn put_back<K: Hash + Eq, V>(map: &mut HashMap<K, V>, old: HashMap<K, Option<V>>) {
+    for (k, v) in old {
+        match v {
+            Some(v) => { map.insert(k, v); },
+            None => { map.remove(&k); },
+        }
+    }
+}
+
+#[test]
+fn push_pop() {
+    let rules = rise_rules(SubstMethod::SmallStep);
+    let mut eg = EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app (lam s0 (app (var s0) (var s0))) (lam s1 (var s1)))").unwrap());
+    eg.check();
+
+    // the SlotAllocator isn't rolled back, so that slots created in between stay unique.
+    let save = |eg: &EGraph<RiseENode>| eg.save(true).lines().filter(|l| !l.starts_with("allocator")).collect::<Vec<_>>().join("\n");
+    let before = save(&eg);
+
+    let mut ex = IncrementalExtractor::new(&eg, AstSize);
+
+    eg.push();
+    eg.push();
+    do_rewrites(&mut eg, &rules);
+    eg.commit();
+    do_rewrites(&mut eg, &rules);
+    ex.update(&eg);
+    // finds within the scope must not compress the older unionfind entries.
+    eg.check();
+    assert_eq!(eg.scope_depth(), 1);
+    eg.pop();
+    assert_eq!(eg.scope_depth(), 0);
+
+    // `check` compresses the unionfind, as it did before the `push`.
+    eg.check();
+    assert_eq!(save(&eg), before);
+
+    // the extractor forgets what it learned from the speculative rewrites.
+    ex.update(&eg);
+    let fresh = IncrementalExtractor::new(&eg, AstSize);
+    fn size(re: &RecExpr<RiseENode>) -> usize { 1 + re.children.iter().map(size).sum::<usize>() }
+    assert_eq!(size(&ex.extract(i.clone(), &eg).unwrap()), size(&fresh.extract(i.clone(), &eg).unwrap()));
+
+    // snapshots work the same way.
+    let s = eg.snapshot();
+    do_rewrites(&mut eg, &rules);
+    eg.check();
+    eg.restore(&s);
+    eg.check();
+    assert_eq!(save(&eg), before);
+}
+
+#[test]
+fn push_pop_nested() {
+    let rules = rise_rules(SubstMethod::SmallStep);
+    let mut eg = EGraph::<RiseENode>::new();
+    eg.add_expr(RecExpr::parse("(app (lam s0 (app (var s0) (var s0))) (lam s1 (var s1)))").unwrap());
+    let save = |eg: &EGraph<RiseENode>| eg.save(true).lines().filter(|l| !l.starts_with("allocator")).collect::<Vec<_>>().join("\n");
+    let before = save(&eg);
+
+    eg.push();
+    do_rewrites(&mut eg, &rules);
+    let middle = save(&eg);
+
+    // a restore within a scope turns it into a copy.
+    eg.push();
+    let s = eg.snapshot();
+    do_rewrites(&mut eg, &rules);
+    eg.restore(&s);
+    do_rewrites(&mut eg, &rules);
+    eg.pop();
+    eg.check();
+    assert_eq!(save(&eg), middle);
+
+    // committing a copy into a logged scope.
+    eg.push();
+    eg.restore(&s);
+    do_rewrites(&mut eg, &rules);
+    eg.commit();
+    eg.pop();
+    eg.check();
+    assert_eq!(save(&eg), before);
+}
+
+#[test]
+#[should_panic(expected = "snapshot of a different e-graph")]
+fn restore_foreign_snapshot() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let other = EGraph::<RiseENode>::new();
+    eg.restore(&other.snapshot());
+}
+
+#[test]
+fn pop_undoes_path_compression() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let [a, b, c, d] = ["sym_a", "sym_b", "sym_c", "sym_d"].map(|x| eg.add_expr(RecExpr::parse(x).unwrap()));
+    eg.union(&a, &b);
+    eg.union(&c, &d);
+    eg.check();
+
+    eg.push();
+    eg.union(&a, &c);
+    for x in [&a, &b, &c, &d] {
+        eg.find_applied_id(x);
+    }
+    eg.pop();
+    eg.check();
+
+    assert!(eg.eq(&a, &b));
+    assert!(eg.eq(&c, &d));
+    for x in [&a, &b] {
+        for y in [&c, &d] {
+            assert!(!eg.eq(x, y));
+        }
+    }
+}
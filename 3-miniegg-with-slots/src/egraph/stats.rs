@@ -0,0 +1,124 @@
+use crate::*;
+
+use std::collections::BTreeMap;
+
+// A read-only view of a single e-class.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClassInfo {
+    pub id: Id,
+    pub slots: HashSet<Slot>,
+
+    // The slots of the syn_enode that turned out to be redundant since the class was created.
+    pub redundant_slots: HashSet<Slot>,
+
+    pub num_nodes: usize,
+
+    // the number of e-node shapes referring to this class.
+    pub num_parents: usize,
+
+    pub group_order: usize,
+    pub generators: Vec<Perm>,
+}
+
+// Histograms map a value to the number of classes having that value.
+pub type Histogram = BTreeMap<usize, usize>;
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct EGraphStats {
+    pub num_classes: usize,
+    pub num_nodes: usize,
+    pub nodes_per_class: Histogram,
+    pub slots_per_class: Histogram,
+    pub redundant_slots_per_class: Histogram,
+    pub parents_per_class: Histogram,
+    pub group_orders: Histogram,
+}
+
+impl<L: Language> EGraph<L> {
+    pub fn class_info(&self, i: Id) -> ClassInfo {
+        let i = self.find_id(i);
+        let c = &self.classes[&i];
+        let mut generators: Vec<Perm> = c.group.generators().into_iter().map(|p| p.0).collect();
+        generators.sort();
+        ClassInfo {
+            id: i,
+            slots: c.slots.clone(),
+            redundant_slots: &c.syn_enode.slots() - &c.slots,
+            num_nodes: c.nodes.len(),
+            num_parents: c.usages.len(),
+            group_order: c.group.count(),
+            generators,
+        }
+    }
+
+    // The infos of all classes, ordered by their ids.
+    pub fn class_infos(&self) -> Vec<ClassInfo> {
+        self.ids().into_iter().map(|i| self.class_info(i)).collect()
+    }
+
+    pub fn stats(&self) -> EGraphStats {
+        let mut s = EGraphStats::default();
+        for c in self.class_infos() {
+            s.num_classes += 1;
+            s.num_nodes += c.num_nodes;
+            *s.nodes_per_class.entry(c.num_nodes).or_default() += 1;
+            *s.slots_per_class.entry(c.slots.len()).or_default() += 1;
+            *s.redundant_slots_per_class.entry(c.redundant_slots.len()).or_default() += 1;
+            *s.parents_per_class.entry(c.num_parents).or_default() += 1;
+            *s.group_orders.entry(c.group_order).or_default() += 1;
+        }
+        s
+    }
+}
+
+impl std::fmt::Display for EGraphStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{} classes, {} nodes", self.num_classes, self.num_nodes)?;
+        let rows = [
+            ("nodes", &self.nodes_per_class),
+            ("slots", &self.slots_per_class),
+            ("redundant slots", &self.redundant_slots_per_class),
+            ("parents", &self.parents_per_class),
+            ("group order", &self.group_orders),
+        ];
+        for (name, h) in rows {
+            let entries = h.iter().map(|(x, n)| format!("{x}:{n}")).collect::<Vec<_>>().join(" ");
+            writeln!(f, "{name:<16}{entries}")?;
+        }
+        Ok(())
+    }
+}
+
+#[test]
+fn class_infos() {
+    let mut eg = EGraph::<RiseENode>::new();
+    let a = eg.add_expr(RecExpr::parse("(app (var s1) (var s2))").unwrap());
+    let b = eg.add_expr(RecExpr::parse("(app (var s2) (var s1))").unwrap());
+    eg.union(&a, &b);
+
+    let info = eg.class_info(a.id);
+    assert_eq!(info.slots.len(), 2);
+    assert_eq!(info.num_nodes, 1);
+    assert_eq!(info.group_order, 2);
+    assert_eq!(info.generators.len(), 1);
+    assert!(info.redundant_slots.is_empty());
+
+    let c = eg.add_expr(RecExpr::parse("(app sym_f (var s3))").unwrap());
+    let d = eg.add_expr(RecExpr::parse("(app sym_f (var s4))").unwrap());
+    eg.union(&c, &d);
+    let info = eg.class_info(c.id);
+    assert!(info.slots.is_empty());
+    assert_eq!(info.redundant_slots.len(), 1);
+    assert_eq!(info.num_nodes, 1);
+
+    // `sym_f` is used by the class of `c`.
+    let f = eg.add_expr(RecExpr::parse("sym_f").unwrap());
+    assert_eq!(eg.class_info(f.id).num_parents, 1);
+
+    let s = eg.stats();
+    assert_eq!(s.num_classes, eg.ids().len());
+    assert_eq!(s.num_nodes, eg.total_number_of_nodes());
+    assert_eq!(s.group_orders[&2], 1);
+    assert_eq!(s.nodes_per_class.values().sum::<usize>(), s.num_classes);
+    assert!(s.to_string().contains("group order"));
+}
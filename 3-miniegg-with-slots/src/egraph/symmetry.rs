@@ -0,0 +1,141 @@
+use crate::*;
+
+// The symmetries of an e-class, expressed in the slots of an AppliedId pointing to it.
+// Every permutation of the group maps these slots to themselves, and leaves the e-class invariant.
+#[derive(Clone, Debug)]
+pub struct Symmetries {
+    slots: HashSet<Slot>,
+    group: Group<Perm>,
+}
+
+impl Symmetries {
+    pub fn slots(&self) -> &HashSet<Slot> {
+        &self.slots
+    }
+
+    pub fn group(&self) -> &Group<Perm> {
+        &self.group
+    }
+
+    // The generators of the group, excluding the identity.
+    pub fn generators(&self) -> Vec<SlotMap> {
+        let mut out: Vec<SlotMap> = self.group.generators().into_iter().collect();
+        out.sort();
+        out
+    }
+
+    // The order of the group.
+    pub fn count(&self) -> usize {
+        self.group.count()
+    }
+
+    // `p` has to be a permutation of `self.slots()`, otherwise this returns false.
+    pub fn contains(&self, p: &SlotMap) -> bool {
+        p.is_perm() && p.keys() == self.slots && self.group.contains(p)
+    }
+
+    // Whether swapping `a` and `b` (and keeping all other slots) is a symmetry.
+    pub fn swaps(&self, a: Slot, b: Slot) -> bool {
+        if !self.slots.contains(&a) || !self.slots.contains(&b) { return false; }
+
+        let mut p = SlotMap::identity(&self.slots);
+        p.insert(a, b);
+        p.insert(b, a);
+        self.group.contains(&p)
+    }
+
+    // All slots that `s` can be mapped to by a symmetry.
+    // Returns the empty set, if `s` is not a slot of the AppliedId.
+    pub fn orbit(&self, s: Slot) -> HashSet<Slot> {
+        if !self.slots.contains(&s) { return HashSet::default(); }
+
+        self.group.orbit(s)
+    }
+}
+
+impl<L: Language> EGraph<L> {
+    // The symmetry group of the e-class of `a`, in terms of the slots of `a`.
+    // Redundant slots of `a` are not part of the result.
+    pub fn symmetries(&self, a: &AppliedId) -> Symmetries {
+        let a = self.find_applied_id(a);
+        let slots = a.slots();
+        let m_inv = a.m.inverse();
+
+        // conjugate the class group by `a.m`, i.e. "m^-1 p m".
+        let generators = self.classes[&a.id].group.generators().into_iter()
+            .map(|p| m_inv.compose(&p.0).compose(&a.m))
+            .collect();
+        let group = Group::new(&SlotMap::identity(&slots), generators);
+        Symmetries { slots, group }
+    }
+
+    // Whether the e-class of `a` has a symmetry other than the identity.
+    pub fn has_symmetries(&self, a: &AppliedId) -> bool {
+        self.classes[&self.find_id(a.id)].group.count() > 1
+    }
+}
+
+#[test]
+fn symmetries_of_class() {
+    let s = Slot::new;
+    let mut eg = EGraph::<RiseENode>::new();
+    let a = eg.add_expr(RecExpr::parse("(app (app (var s1) (var s2)) (var s3))").unwrap());
+    let b = eg.add_expr(RecExpr::parse("(app (app (var s2) (var s1)) (var s3))").unwrap());
+    eg.union(&a, &b);
+
+    let sym = eg.symmetries(&a);
+    assert_eq!(sym.count(), 2);
+    assert!(sym.swaps(s(1), s(2)));
+    assert!(!sym.swaps(s(1), s(3)));
+    assert_eq!(sym.orbit(s(1)), [s(1), s(2)].into_iter().collect());
+    assert_eq!(sym.orbit(s(3)), singleton_set(s(3)));
+    assert_eq!(sym.generators(), vec![SlotMap::from_pairs(&[(s(1), s(2)), (s(2), s(1)), (s(3), s(3))])]);
+
+    // the same class, seen through renamed slots.
+    let renamed = a.apply_slotmap(&SlotMap::from_pairs(&[(s(1), s(7)), (s(2), s(8)), (s(3), s(9))]));
+    let sym = eg.symmetries(&renamed);
+    assert!(sym.swaps(s(7), s(8)));
+    assert!(!sym.swaps(s(8), s(9)));
+    assert!(sym.contains(&SlotMap::identity(sym.slots())));
+    assert!(!sym.contains(&SlotMap::from_pairs(&[(s(7), s(8)), (s(8), s(7))])));
+}
+
+#[test]
+fn visit_variants_prunes() {
+    let s = Slot::new;
+    let p = |x| RecExpr::<RiseENode>::parse(x).unwrap();
+    let mut eg = EGraph::<RiseENode>::new();
+    let a = eg.add_expr(p("(app (app (var s1) (var s2)) (var s3))"));
+    let b = eg.add_expr(p("(app (app (var s2) (var s1)) (var s3))"));
+    let c = eg.add_expr(p("(app (app (var s1) (var s3)) (var s2))"));
+    eg.union(&a, &b);
+    eg.union(&a, &c);
+    let a = eg.find_applied_id(&a);
+    assert_eq!(eg.symmetries(&a).count(), 6);
+
+    // only the variants mapping the slot at s1 to s3 are visited.
+    let at_s1 = a.m.inverse()[s(1)];
+    let mut visited = Vec::new();
+    eg.visit_applied_id_variants(&a, |x, y| x != at_s1 || y == s(3), |v| visited.push(v));
+    assert_eq!(visited.len(), 2);
+    assert!(visited.iter().all(|v| v.m[at_s1] == s(3) && v.slots() == a.slots()));
+    assert_ne!(visited[0], visited[1]);
+}
+
+#[test]
+fn symmetry_reaches_grandparents() {
+    let p = |x: &str| RecExpr::<RiseENode>::parse(x).unwrap();
+
+    // the symmetry of the inner class is found for its parent during rebuild, and then changes the shape of the lambda.
+    for lam in ["(lam s4 (app (app (var s4) (var s5)) sym_y))", "(lam s5 (app (app (var s4) (var s5)) sym_y))"] {
+        let mut eg = EGraph::<RiseENode>::new();
+        let i = eg.add_expr(p(lam));
+        let a = eg.add_expr(p("(app (var s1) (var s2))"));
+        let b = eg.add_expr(p("(app (var s2) (var s1))"));
+        eg.union(&a, &b);
+        assert!(eg.violations().is_empty());
+        let sym = p(&lam.replace("(var s4) (var s5)", "(var s5) (var s4)"));
+        let j = eg.add_expr(sym);
+        assert!(eg.eq(&i, &j));
+    }
+}
@@ -1,23 +1,49 @@
 use crate::*;
 
+// The outcome of a union.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UnionStatus {
+    // the e-graph learned something new: e-classes were merged, slots became redundant, or a class gained a symmetry.
+    Changed,
+
+    // the equation was already known.
+    Unchanged,
+
+    // no union happened, as an e-node couldn't be added due to the budget of the e-graph, see `budget_exhausted`.
+    // The sub-terms added until then stay in the e-graph.
+    BudgetExhausted,
+
+    // nothing happened, as the e-graph is cancelled, see `is_cancelled`.
+    Cancelled,
+}
+
 impl<L: Language> EGraph<L> {
-    pub fn union(&mut self, l: &AppliedId, r: &AppliedId) -> bool {
+    // Unions of existing e-classes don't grow the e-graph, so they are allowed even if the budget is exhausted.
+    pub fn union(&mut self, l: &AppliedId, r: &AppliedId) -> UnionStatus {
         self.union_justified(l, r, None)
     }
 
-    pub fn union_justified(&mut self, l: &AppliedId, r: &AppliedId, j: Option<String>) -> bool {
+    pub fn union_justified(&mut self, l: &AppliedId, r: &AppliedId, j: Option<String>) -> UnionStatus {
         let subst = [(String::from("a"), l.clone()),
                      (String::from("b"), r.clone())]
                         .into_iter().collect();
         let a = Pattern::parse("?a").unwrap();
         let b = Pattern::parse("?b").unwrap();
 
+        // instantiating pattern variables doesn't add e-nodes, so this can't run into the budget.
         self.union_instantiations(&a, &b, &subst, j)
     }
 
-    pub fn union_instantiations(&mut self, from_pat: &Pattern<L>, to_pat: &Pattern<L>, subst: &Subst, justification: Option<String>) -> bool {
-        let a = pattern_subst(self, from_pat, subst);
-        let b = pattern_subst(self, to_pat, subst);
+    pub fn union_instantiations(&mut self, from_pat: &Pattern<L>, to_pat: &Pattern<L>, subst: &Subst, justification: Option<String>) -> UnionStatus {
+        self.with_slot_allocator(|eg| eg.union_instantiations_impl(from_pat, to_pat, subst, justification))
+    }
+
+    fn union_instantiations_impl(&mut self, from_pat: &Pattern<L>, to_pat: &Pattern<L>, subst: &Subst, justification: Option<String>) -> UnionStatus {
+        if self.is_cancelled() { return UnionStatus::Cancelled; }
+
+        // the budget is checked for every e-node that is new, so instantiating existing terms is always possible.
+        let Some(a) = try_pattern_subst(self, from_pat, subst) else { return UnionStatus::BudgetExhausted };
+        let Some(b) = try_pattern_subst(self, to_pat, subst) else { return UnionStatus::BudgetExhausted };
 
         let syn_a = self.synify_app_id(a.clone());
         let syn_b = self.synify_app_id(b.clone());
@@ -26,7 +52,7 @@ impl<L: Language> EGraph<L> {
 
         let out = self.union_internal(&a, &b, proof);
         self.rebuild();
-        out
+        if out { UnionStatus::Changed } else { UnionStatus::Unchanged }
     }
 
     pub(in crate::egraph) fn union_internal(&mut self, l: &AppliedId, r: &AppliedId, proof: ProvenEq) -> bool {
@@ -39,7 +65,7 @@ impl<L: Language> EGraph<L> {
         let a = self.prove_transitivity(a, proof);
         let a = self.prove_transitivity(a, p_r);
         let proof = a;
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(proof.l.id, l.id);
             assert_eq!(proof.r.id, r.id);
         }
@@ -69,7 +95,7 @@ impl<L: Language> EGraph<L> {
             // r.m :: slots(id) -> X
             // perm :: slots(id) -> slots(id)
             let perm = l.m.compose(&r.m.inverse());
-            if CHECKS {
+            if self.check_level >= CheckLevel::Cheap {
                 assert!(perm.is_perm());
                 assert_eq!(&perm.keys(), &self.classes[&id].slots);
             }
@@ -78,12 +104,14 @@ impl<L: Language> EGraph<L> {
             assert_eq!(proven_perm.1.l.id, id);
 
             proven_perm.check();
-            let grp = &mut self.classes.get_mut(&id).unwrap().group;
+            let grp = &mut self.class_mut(id).group;
             if grp.contains(&proven_perm.to_slotmap()) { return false; }
 
+            let perm = proven_perm.0.clone();
             grp.add(proven_perm);
 
             self.touched_class(id);
+            self.emit(|| Event::Symmetry { id, perm });
 
             true
         } else {
@@ -107,7 +135,7 @@ impl<L: Language> EGraph<L> {
     // proof.l should be i.
     // proof.r should be missing a few slots.
     fn record_redundancy_witness(&mut self, i: Id, proof: ProvenEq) {
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert!(self.is_alive(i));
             assert_eq!(proof.l.id, i);
         }
@@ -115,13 +143,13 @@ impl<L: Language> EGraph<L> {
         let flipped = prove_symmetry(proof.clone(), &self.proof_registry);
         let new_prf = prove_transitivity(proof, flipped, &self.proof_registry);
 
-        let old_prf = &mut self.classes.get_mut(&i).unwrap().redundancy_proof;
-        *old_prf = prove_transitivity(new_prf, old_prf.clone(), &self.proof_registry);
+        let old_prf = self.classes[&i].redundancy_proof.clone();
+        self.class_mut(i).redundancy_proof = prove_transitivity(new_prf, old_prf, &self.proof_registry);
     }
 
     // We expect `from` to be on the lhs of this equation.
     fn shrink_slots(&mut self, from: &AppliedId, cap: &HashSet<Slot>, proof: ProvenEq) {
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(from.id, proof.l.id);
         }
 
@@ -143,7 +171,7 @@ impl<L: Language> EGraph<L> {
         // cap :: set slots(id)
 
         let syn_slots = &self.syn_slots(id);
-        let c = self.classes.get_mut(&id).unwrap();
+        let c = self.class_mut(id);
         let grp = &c.group;
 
         let mut final_cap = cap.clone();
@@ -154,6 +182,7 @@ impl<L: Language> EGraph<L> {
             final_cap = &final_cap - &grp.orbit(d);
         }
 
+        let redundant = &c.slots - &cap;
         c.slots = cap.clone();
         let generators = c.group.generators();
         let _ = c;
@@ -173,14 +202,15 @@ impl<L: Language> EGraph<L> {
         let generators = generators.into_iter().map(restrict_proven).collect();
         let identity = ProvenPerm::identity(id, &cap, syn_slots, self.proof_registry.clone());
         identity.check();
-        let c = self.classes.get_mut(&id).unwrap();
+        let c = self.class_mut(id);
         c.group = Group::new(&identity, generators);
 
         self.touched_class(from.id);
+        self.emit(|| Event::Shrunk { id, redundant });
     }
 
     fn assert_ty(&self, m: &SlotMap, keys: &HashSet<Slot>, values: &HashSet<Slot>) {
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert!(m.keys().is_subset(&keys));
             assert!(m.values().is_subset(&values));
         }
@@ -188,7 +218,7 @@ impl<L: Language> EGraph<L> {
 
     // moves everything from `from` to `to`.
     fn move_to(&mut self, from: &AppliedId, to: &AppliedId, proof: ProvenEq) {
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(from.slots(), to.slots());
             assert_eq!(from.id, proof.l.id);
             assert_eq!(to.id, proof.r.id);
@@ -199,7 +229,8 @@ impl<L: Language> EGraph<L> {
         self.assert_ty(&map, &self.slots(to.id), &self.slots(from.id));
 
         let app_id = self.mk_sem_applied_id(to.id, map.clone());
-        self.unionfind_set(from.id, app_id, proof);
+        self.unionfind_set(from.id, app_id.clone(), proof);
+        self.emit(|| Event::Merged { from: from.id, into: app_id });
 
         // who updates the usages? raw_add_to_class & raw_remove_from_class do that.
 
@@ -232,7 +263,7 @@ impl<L: Language> EGraph<L> {
                 } else { None }
             }).collect();
 
-            if CHECKS {
+            if self.check_level >= CheckLevel::Cheap {
                 assert!(perm.is_perm());
                 assert_eq!(perm.keys(), self.classes[&to.id].slots);
             }
@@ -247,11 +278,18 @@ impl<L: Language> EGraph<L> {
             ProvenPerm(new_perm, new_proof, self.proof_registry.clone())
         };
 
-        let set = self.classes[&from.id].group.generators()
+        let set: Vec<ProvenPerm> = self.classes[&from.id].group.generators()
             .into_iter()
             .map(change_proven_permutation_from_from_to_to)
             .collect();
-        self.classes.get_mut(&to.id).unwrap().group.add_set(set);
+        let new_perms: Vec<Perm> = set.iter()
+            .filter(|p| !self.classes[&to.id].group.contains(&p.0))
+            .map(|p| p.0.clone())
+            .collect();
+        self.class_mut(to.id).group.add_set(set.into_iter().collect());
+        for perm in new_perms {
+            self.emit(|| Event::Symmetry { id: to.id, perm });
+        }
 
         // touched because the group might have grown.
         self.touched_class(to.id);
@@ -260,13 +298,20 @@ impl<L: Language> EGraph<L> {
         self.touched_class(from.id);
     }
 
+    // Stops early if the e-graph is cancelled, see `is_cancelled`.
+    // Then the remaining e-nodes stay pending, and the e-graph isn't congruence-closed until a later `rebuild` completes.
     pub fn rebuild(&mut self) {
-        if CHECKS { self.check(); }
-        while let Some(sh) = self.pending.iter().cloned().next() {
-            self.pending.remove(&sh);
+        self.with_slot_allocator(|eg| eg.rebuild_impl())
+    }
+
+    fn rebuild_impl(&mut self) {
+        if self.check_level >= CheckLevel::PerRebuild { self.check(); }
+        // `pending` remembers the insertion order, so the e-nodes are processed in a reproducible order.
+        while let Some(sh) = self.pending.pop() {
             self.handle_pending(sh);
+            if self.is_cancelled() { break; }
 
-            if CHECKS { self.check(); }
+            if self.check_level >= CheckLevel::PerPending { self.check(); }
         }
     }
 
@@ -302,7 +347,7 @@ impl<L: Language> EGraph<L> {
         let prf = self.prove_transitivity(neg_leader_prf.clone(), self.prove_transitivity(cong, leader_prf.clone()));
 
         let leader_inv = leader.m.inverse();
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             let ty = self.syn_slots(src_id);
             assert!(leader_inv.keys().is_subset(&ty));
         }
@@ -362,7 +407,7 @@ impl<L: Language> EGraph<L> {
         let syn_slots = self.syn_slots(src_id);
         let src_identity = AppliedId::new(src_id, Perm::identity(&syn_slots));
         let syn_node = self.get_syn_node(&src_identity);
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(&syn_slots, &syn_node.slots());
         }
 
@@ -388,7 +433,7 @@ impl<L: Language> EGraph<L> {
                 // no need to add the identity permutation.
                 if perm.iter().all(|(x, y)| x == y) { continue; }
 
-                if CHECKS { assert!(perm.is_perm()); }
+                if self.check_level >= CheckLevel::Cheap { assert!(perm.is_perm()); }
 
                 let mut combined_prfs = Vec::new();
                 for (old_to_new_ids, perm_prf) in prfs.iter().zip(prfs2.iter()) {
@@ -402,7 +447,7 @@ impl<L: Language> EGraph<L> {
                 // src_id[...] == src_id[...]
 
                 let prf = self.prove_congruence(src_id, src_id, &combined_prfs);
-                if CHECKS {
+                if self.check_level >= CheckLevel::Cheap {
                     assert_eq!(prf.l.id, src_id);
                     assert_eq!(prf.r.id, src_id);
                 }
@@ -413,17 +458,24 @@ impl<L: Language> EGraph<L> {
 
                 let slots = self.slots(i);
                 let syn_slots = self.syn_slots(i);
-                if CHECKS {
+                if self.check_level >= CheckLevel::Cheap {
                     assert_eq!(prf.l.id, i);
                     assert_eq!(prf.r.id, i);
                 }
                 let proven_perm = ProvenPerm(perm, prf, self.proof_registry.clone());
 
-                if CHECKS {
+                if self.check_level >= CheckLevel::PerRebuild {
                     proven_perm.check();
                 }
-                let grp = &mut self.classes.get_mut(&i).unwrap().group;
-                grp.add(proven_perm);
+                let perm = proven_perm.0.clone();
+                let grp = &mut self.class_mut(i).group;
+                if grp.add(proven_perm) {
+                    // the shapes of the parents depend on the group, so they have to be re-canonicalized.
+                    self.touched_class(i);
+                    self.emit(|| Event::Symmetry { id: i, perm });
+                } else {
+                    self.touch(i);
+                }
             }
         }
     }
@@ -438,7 +490,7 @@ impl<L: Language> EGraph<L> {
         let c = c.apply_slotmap_fresh(&b.m); // TODO why on earth does it fail if I remove this? This should do literally nothing.
         let c_node = self.get_syn_node(&c);
         let (t2, vec_p2) = self.proven_shape(&c_node);
-        if CHECKS {
+        if self.check_level >= CheckLevel::Cheap {
             assert_eq!(&t.0, &t2.0);
         }
 
@@ -458,6 +510,7 @@ impl<L: Language> EGraph<L> {
 
     // upon touching an e-class, you need to update all usages of it.
     fn touched_class(&mut self, i: Id) {
+        self.touch(i);
         for sh in &self.classes[&i].usages {
             self.pending.insert(sh.clone());
         }
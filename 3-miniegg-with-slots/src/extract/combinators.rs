@@ -0,0 +1,163 @@
+use crate::*;
+
+// Generic building blocks for cost functions.
+// Operators are identified by the name returned from `Language::to_op`.
+
+fn op_name<L: Language>(enode: &L) -> String {
+    enode.to_op().0
+}
+
+// A cost that can be "Infinite", i.e. worse than every finite cost.
+// The derived Ord puts every Finite(_) below Infinite.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub enum Bounded<C> {
+    Finite(C),
+    Infinite,
+}
+
+impl<C> Bounded<C> {
+    pub fn finite(&self) -> Option<&C> {
+        match self {
+            Bounded::Finite(c) => Some(c),
+            Bounded::Infinite => None,
+        }
+    }
+}
+
+// Compares by the first cost function, and uses the second one to break ties.
+#[derive(Default, Clone, Debug)]
+pub struct Lexicographic<A, B>(pub A, pub B);
+
+impl<L: Language, A: CostFunction<L>, B: CostFunction<L>> CostFunction<L> for Lexicographic<A, B> {
+    type Cost = (A::Cost, B::Cost);
+
+    fn cost<C>(&self, enode: &L, costs: C) -> Self::Cost where C: Fn(Id) -> Self::Cost {
+        let a = self.0.cost(enode, |i| costs(i).0);
+        let b = self.1.cost(enode, |i| costs(i).1);
+        (a, b)
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct WeightedCost {
+    // total == wa * a + wb * b.
+    // This comes first, so that the derived Ord compares by it.
+    pub total: u64,
+    pub a: u64,
+    pub b: u64,
+}
+
+// Combines two u64-valued cost functions into `wa * a + wb * b`.
+#[derive(Clone, Debug)]
+pub struct WeightedSum<A, B> {
+    pub a: A,
+    pub wa: u64,
+    pub b: B,
+    pub wb: u64,
+}
+
+impl<A, B> WeightedSum<A, B> {
+    pub fn new(a: A, wa: u64, b: B, wb: u64) -> Self {
+        WeightedSum { a, wa, b, wb }
+    }
+}
+
+impl<L: Language, A: CostFunction<L, Cost=u64>, B: CostFunction<L, Cost=u64>> CostFunction<L> for WeightedSum<A, B> {
+    type Cost = WeightedCost;
+
+    fn cost<C>(&self, enode: &L, costs: C) -> WeightedCost where C: Fn(Id) -> WeightedCost {
+        let a = self.a.cost(enode, |i| costs(i).a);
+        let b = self.b.cost(enode, |i| costs(i).b);
+        let total = a.saturating_mul(self.wa).saturating_add(b.saturating_mul(self.wb));
+        WeightedCost { total, a, b }
+    }
+}
+
+// Like AstSize, but every operator has its own weight.
+// Operators without an entry in the table get the `default` weight.
+#[derive(Clone, Debug)]
+pub struct OpWeights {
+    default: u64,
+    weights: HashMap<String, u64>,
+}
+
+impl OpWeights {
+    pub fn new(default: u64) -> Self {
+        OpWeights { default, weights: HashMap::default() }
+    }
+
+    pub fn set(&mut self, op: &str, weight: u64) {
+        self.weights.insert(op.to_string(), weight);
+    }
+
+    pub fn with(mut self, op: &str, weight: u64) -> Self {
+        self.set(op, weight);
+        self
+    }
+
+    pub fn weight(&self, op: &str) -> u64 {
+        self.weights.get(op).copied().unwrap_or(self.default)
+    }
+}
+
+impl Default for OpWeights {
+    // Every operator costs 1, this makes it equivalent to AstSize.
+    fn default() -> Self {
+        OpWeights::new(1)
+    }
+}
+
+impl<L: Language> CostFunction<L> for OpWeights {
+    type Cost = u64;
+
+    fn cost<C>(&self, enode: &L, costs: C) -> u64 where C: Fn(Id) -> u64 {
+        let mut s = self.weight(&op_name(enode));
+        for x in enode.applied_id_occurences() {
+            s = s.saturating_add(costs(x.id));
+        }
+        s
+    }
+}
+
+// Assigns Bounded::Infinite to every e-node using a forbidden operator, or having an Infinite child.
+// All other e-nodes are priced by `inner`.
+#[derive(Clone, Debug)]
+pub struct Forbid<CF> {
+    pub inner: CF,
+    pub ops: HashSet<String>,
+}
+
+impl<CF> Forbid<CF> {
+    pub fn new(inner: CF, ops: &[&str]) -> Self {
+        let ops = ops.iter().map(|x| x.to_string()).collect();
+        Forbid { inner, ops }
+    }
+
+    pub fn forbid(&mut self, op: &str) {
+        self.ops.insert(op.to_string());
+    }
+
+    pub fn allow(&mut self, op: &str) {
+        self.ops.remove(op);
+    }
+}
+
+impl<L: Language, CF: CostFunction<L>> CostFunction<L> for Forbid<CF> {
+    type Cost = Bounded<CF::Cost>;
+
+    fn cost<C>(&self, enode: &L, costs: C) -> Self::Cost where C: Fn(Id) -> Self::Cost {
+        if self.ops.contains(&op_name(enode)) {
+            return Bounded::Infinite;
+        }
+
+        let mut child_costs = HashMap::default();
+        for x in enode.applied_id_occurences() {
+            match costs(x.id) {
+                Bounded::Finite(c) => { child_costs.insert(x.id, c); },
+                Bounded::Infinite => return Bounded::Infinite,
+            }
+        }
+
+        Bounded::Finite(self.inner.cost(enode, |i| child_costs[&i].clone()))
+    }
+}
@@ -0,0 +1,147 @@
+use crate::*;
+
+use std::collections::VecDeque;
+
+// An Extractor that survives changes to the e-graph.
+// Calling `update` only revisits the classes touched since the last update, and the classes whose costs improve as a consequence.
+//
+// This relies on costs never increasing: adding e-nodes and unioning e-classes only makes more terms available.
+pub struct IncrementalExtractor<L: Language, CF: CostFunction<L>> {
+    cf: CF,
+
+    // same as in Extractor.
+    map: HashMap<Id, WithOrdRev<L, CF::Cost>>,
+
+    // the e-graph generation, that `map` is up-to-date with.
+    generation: usize,
+
+    // the e-graph epoch of `generation`.
+    epoch: usize,
+}
+
+impl<L: Language, CF: CostFunction<L>> IncrementalExtractor<L, CF> {
+    pub fn new(eg: &EGraph<L>, cf: CF) -> Self {
+        let mut out = IncrementalExtractor {
+            cf,
+            map: HashMap::default(),
+            generation: 0,
+            epoch: eg.epoch(),
+        };
+        out.update(eg);
+        out
+    }
+
+    pub fn update(&mut self, eg: &EGraph<L>) {
+        if eg.check_level() >= CheckLevel::PerRebuild { eg.check(); }
+
+        // The e-graph was rolled back, so costs might have increased. We start from scratch.
+        if self.epoch != eg.epoch() {
+            self.map.clear();
+            self.generation = 0;
+            self.epoch = eg.epoch();
+        }
+
+        let touched = eg.touched_since(self.generation);
+        self.generation = eg.generation();
+
+        // The e-nodes stored for touched classes might be outdated, as their class could be dead or have lost some slots.
+        // Their costs are still valid though, so we move them to their current class.
+        let mut stale = Vec::new();
+        for i in &touched {
+            if let Some(x) = self.map.remove(i) {
+                stale.push(x);
+            }
+        }
+        for WithOrdRev(enode, c) in stale {
+            // enode.slots() might contain newly redundant slots, we refresh them.
+            let enode = eg.find_enode(&enode);
+            let i = eg.lookup(&enode).unwrap();
+            let enode = enode.refresh_internals(i.slots()).apply_slotmap_fresh(&i.m.inverse());
+            self.improve(i.id, enode, c, eg);
+        }
+
+        // touched classes might have gained new e-nodes.
+        let mut queue = Worklist::default();
+        let mut sorted: Vec<Id> = touched.iter().map(|i| eg.find_id(*i)).collect();
+        sorted.sort();
+        for i in sorted {
+            queue.push(i);
+        }
+
+        while let Some(i) = queue.pop() {
+            let mut improved = false;
+            for x in eg.enodes(i) {
+                if !x.ids().iter().all(|j| self.map.contains_key(j)) { continue; }
+
+                let x = eg.class_nf(&x);
+                let c = self.cf.cost(&x, |j| self.map[&j].1.clone());
+                improved |= self.improve(i, x, c, eg);
+            }
+
+            if improved {
+                for x in eg.usages(i) {
+                    queue.push(eg.lookup(&x).unwrap().id);
+                }
+            }
+        }
+    }
+
+    // Like `Extractor::extract`, this fails for classes that weren't reached, e.g. as they don't contain a finite term.
+    // An e-node is only chosen once its children were reached, so they can always be extracted.
+    pub fn extract(&self, i: AppliedId, eg: &EGraph<L>) -> Option<RecExpr<L>> {
+        if !self.map.contains_key(&eg.find_id(i.id)) { return None; }
+        Some(eg.slot_allocator().scope(|| extract_from_map(&self.map, i, eg)))
+    }
+
+    // stores `enode` as the optimal e-node of `i`, if it's strictly better than the current one.
+    fn improve(&mut self, i: Id, enode: L, c: CF::Cost, eg: &EGraph<L>) -> bool {
+        if let Some(WithOrdRev(_, old)) = self.map.get(&i) {
+            if &c >= old { return false; }
+        }
+
+        // If costs are not strictly increasing (eg. when everything is Infinite), choosing `enode` might create a cycle.
+        let child_max = enode.ids().iter().filter_map(|j| self.map.get(j)).map(|x| x.1.clone()).max();
+        if child_max.map(|x| x >= c).unwrap_or(false) && self.reaches(&enode, i, eg) {
+            return false;
+        }
+
+        self.map.insert(i, WithOrdRev(enode, c));
+        true
+    }
+
+    // checks whether the currently chosen terms below `enode` contain the e-class `target`.
+    fn reaches(&self, enode: &L, target: Id, eg: &EGraph<L>) -> bool {
+        let mut stack = enode.ids();
+        let mut visited = HashSet::default();
+        while let Some(j) = stack.pop() {
+            let j = eg.find_id(j);
+            if j == target { return true; }
+            if !visited.insert(j) { continue; }
+            if let Some(WithOrdRev(x, _)) = self.map.get(&j) {
+                stack.extend(x.ids());
+            }
+        }
+        false
+    }
+}
+
+// A FIFO queue that contains each Id at most once.
+#[derive(Default)]
+pub(crate) struct Worklist {
+    queue: VecDeque<Id>,
+    set: HashSet<Id>,
+}
+
+impl Worklist {
+    pub(crate) fn push(&mut self, i: Id) {
+        if self.set.insert(i) {
+            self.queue.push_back(i);
+        }
+    }
+
+    pub(crate) fn pop(&mut self) -> Option<Id> {
+        let i = self.queue.pop_front()?;
+        self.set.remove(&i);
+        Some(i)
+    }
+}
@@ -0,0 +1,78 @@
+use crate::*;
+
+// Languages with let-bindings, which can be eliminated by substitution.
+pub trait LetLanguage: Language {
+    // Some(x), if this is `(let x t b)`. Its children are t and b, in this order.
+    fn let_slot(&self) -> Option<Slot>;
+
+    // Some(x), if this is the variable x.
+    fn var_slot(&self) -> Option<Slot>;
+}
+
+// Extracts a term without lets.
+// If the e-class has a let-free term, the smallest such term is returned.
+// Otherwise, the smallest term is extracted and its lets are substituted away.
+pub fn extract_let_free<L: LetLanguage>(i: AppliedId, eg: &EGraph<L>) -> RecExpr<L> {
+    let re = extract_with(i, eg, Lexicographic(AstSizeNoLet::default(), AstSize));
+    eliminate_lets(&re)
+}
+
+// Replaces every `(let x t b)` by b[x := t].
+// The AppliedIds of the resulting nodes are nulled, as the substitution invalidates them.
+pub fn eliminate_lets<L: LetLanguage>(re: &RecExpr<L>) -> RecExpr<L> {
+    let children: Vec<RecExpr<L>> = re.children.iter().map(eliminate_lets).collect();
+    match (re.node.let_slot(), &children[..]) {
+        (Some(x), [t, b]) => subst(b, x, t),
+        _ => RecExpr { node: nullify_app_ids(&re.node), children },
+    }
+}
+
+// The functions below only get let-free terms.
+// Hence every private slot of a node is a binder for all of its children.
+
+// computes b[x := t].
+// Binders of b that occur free in t are renamed, so that they don't capture anything.
+fn subst<L: LetLanguage>(b: &RecExpr<L>, x: Slot, t: &RecExpr<L>) -> RecExpr<L> {
+    if b.node.var_slot() == Some(x) {
+        return t.clone();
+    }
+
+    let prv = b.node.private_slots();
+
+    // x is shadowed.
+    if prv.contains(&x) {
+        return b.clone();
+    }
+
+    let mut b = b.clone();
+    for y in &prv & &as_set(t.free_slots()) {
+        b = rename_binder(&b, y, Slot::fresh());
+    }
+
+    let children = b.children.iter().map(|c| subst(c, x, t)).collect();
+    RecExpr { node: b.node, children }
+}
+
+// renames the binder y of re.node to z.
+fn rename_binder<L: Language>(re: &RecExpr<L>, y: Slot, z: Slot) -> RecExpr<L> {
+    RecExpr {
+        node: rename_slot(&re.node, y, z),
+        children: re.children.iter().map(|c| rename_free(c, y, z)).collect(),
+    }
+}
+
+// renames the free occurences of y in re to z.
+fn rename_free<L: Language>(re: &RecExpr<L>, y: Slot, z: Slot) -> RecExpr<L> {
+    if re.node.private_slots().contains(&y) {
+        return re.clone();
+    }
+    rename_binder(re, y, z)
+}
+
+fn rename_slot<L: Language>(l: &L, y: Slot, z: Slot) -> L {
+    let mut l = l.clone();
+    for s in l.all_slot_occurences_mut() {
+        if *s == y { *s = z; }
+    }
+    l
+}
@@ -6,15 +6,42 @@ pub use cost::*;
 mod with_ord;
 pub use with_ord::*;
 
+mod combinators;
+pub use combinators::*;
+
+mod pareto;
+pub use pareto::*;
+
+mod incremental;
+pub use incremental::*;
+
+mod sketch;
+pub use sketch::*;
+
+mod sample;
+pub use sample::*;
+
+mod let_free;
+pub use let_free::*;
+
+#[cfg(test)]
+mod tst;
+
 use std::collections::BinaryHeap;
 
 pub struct Extractor<L: Language, CF: CostFunction<L>> {
     pub map: HashMap<Id, WithOrdRev<L, CF::Cost>>,
+    cf: CF,
+    cancelled: bool,
 }
 
 impl<L: Language, CF: CostFunction<L>> Extractor<L, CF> {
-    pub fn new(eg: &EGraph<L>) -> Self {
-        eg.check();
+    pub fn new(eg: &EGraph<L>) -> Self where CF: Default {
+        Self::new_with(eg, CF::default())
+    }
+
+    pub fn new_with(eg: &EGraph<L>, cf: CF) -> Self {
+        if eg.check_level() >= CheckLevel::PerRebuild { eg.check(); }
 
         // all the L in `map` and `queue` have to be
         // - in "normal-form", i.e. calling lookup on them yields an identity AppliedId.
@@ -28,13 +55,18 @@ impl<L: Language, CF: CostFunction<L>> Extractor<L, CF> {
             for x in eg.enodes(id) {
                 if x.applied_id_occurences().is_empty() {
                     let x = eg.class_nf(&x);
-                    let c = CF::cost(&x, |_| panic!());
+                    let c = cf.cost(&x, |_| panic!());
                     queue.push(WithOrdRev(x, c));
                 }
             }
         }
 
+        let mut cancelled = false;
         while let Some(WithOrdRev(enode, c)) = queue.pop() {
+            if eg.is_cancelled() {
+                cancelled = true;
+                break;
+            }
             let i = eg.lookup(&enode).unwrap();
             if map.contains_key(&i.id) {
                 continue;
@@ -47,31 +79,54 @@ impl<L: Language, CF: CostFunction<L>> Extractor<L, CF> {
                         continue;
                     }
                     let x = eg.class_nf(&x);
-                    let c = CF::cost(&x, |i| map[&i].1.clone());
+                    let c = cf.cost(&x, |i| map[&i].1.clone());
                     queue.push(WithOrdRev(x, c));
                 }
             }
         }
 
-        Self { map }
+        Self { map, cf, cancelled }
+    }
+
+    pub fn cost_function(&self) -> &CF {
+        &self.cf
     }
 
-    pub fn extract(&self, i: AppliedId, eg: &EGraph<L>) -> RecExpr<L> {
-        let i = eg.find_applied_id(&i);
+    // Whether the e-graph was cancelled during the construction.
+    // Then `map` only contains the classes reached so far, `extract` returns None for the others.
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled
+    }
 
-        let mut children = Vec::new();
+    // the cost of the term returned by `extract(i, eg)`.
+    pub fn cost(&self, i: &AppliedId, eg: &EGraph<L>) -> CF::Cost {
+        self.map[&eg.find_id(i.id)].1.clone()
+    }
 
-        // do I need to refresh some slots here?
-        let l = self.map[&i.id].0.apply_slotmap(&i.m);
-        for child in l.applied_id_occurences() {
-            let n = self.extract(child, eg);
-            children.push(n);
-        }
+    // Fails only for classes that weren't reached due to a cancellation, see `is_cancelled`.
+    // Children of a reached class were reached before it, so they can always be extracted.
+    pub fn extract(&self, i: AppliedId, eg: &EGraph<L>) -> Option<RecExpr<L>> {
+        if !self.map.contains_key(&eg.find_id(i.id)) { return None; }
+        Some(eg.slot_allocator().scope(|| extract_from_map(&self.map, i, eg)))
+    }
+}
 
-        RecExpr {
-            node: l,
-            children,
-        }
+// builds the RecExpr for `i`, given the optimal e-node for each e-class.
+pub(crate) fn extract_from_map<L: Language, C: Ord>(map: &HashMap<Id, WithOrdRev<L, C>>, i: AppliedId, eg: &EGraph<L>) -> RecExpr<L> {
+    let i = eg.find_applied_id(&i);
+
+    let mut children = Vec::new();
+
+    // redundant slots of the stored e-node are not covered by i.m, they get fresh names.
+    let l = map[&i.id].0.apply_slotmap_fresh(&i.m);
+    for child in l.applied_id_occurences() {
+        let n = extract_from_map(map, child, eg);
+        children.push(n);
+    }
+
+    RecExpr {
+        node: l,
+        children,
     }
 }
 
@@ -80,6 +135,11 @@ pub fn ast_size_extract<L: Language>(i: AppliedId, eg: &EGraph<L>) -> RecExpr<L>
 }
 
 // `i` is not allowed to have free variables, hence prefer `Id` over `AppliedId`.
-pub fn extract<L: Language, CF: CostFunction<L>>(i: AppliedId, eg: &EGraph<L>) -> RecExpr<L> {
-    Extractor::<L, CF>::new(eg).extract(i, eg)
+pub fn extract<L: Language, CF: CostFunction<L> + Default>(i: AppliedId, eg: &EGraph<L>) -> RecExpr<L> {
+    extract_with(i, eg, CF::default())
+}
+
+// Panics if the e-graph is cancelled, use `Extractor` to handle that.
+pub fn extract_with<L: Language, CF: CostFunction<L>>(i: AppliedId, eg: &EGraph<L>, cf: CF) -> RecExpr<L> {
+    Extractor::<L, CF>::new_with(eg, cf).extract(i, eg).expect("extract_with: the e-graph was cancelled")
 }
@@ -0,0 +1,211 @@
+use crate::*;
+
+// Extraction for two objectives at once.
+// Instead of a single best term, every e-class gets a Pareto front: the set of all terms that are not dominated in both costs.
+//
+// Both cost functions should be monotone (an e-node never costs less than its children).
+// Otherwise the fixpoint iteration might not terminate, and is cut off after a bounded number of steps.
+
+struct ParetoEntry<L, CA, CB> {
+    cost_a: CA,
+    cost_b: CB,
+
+    // in "normal-form", i.e. calling lookup on it yields an identity AppliedId.
+    enode: L,
+
+    // choices[k] is the index of the entry chosen for the k-th AppliedId of `enode` in the front of its e-class.
+    choices: Vec<usize>,
+
+    // Entries are never removed, so that the indices in `choices` stay valid.
+    // Instead they are marked as dominated, once a better entry shows up.
+    dominated: bool,
+}
+
+type Front<L, A, B> = Vec<ParetoEntry<L, <A as CostFunction<L>>::Cost, <B as CostFunction<L>>::Cost>>;
+
+pub struct ParetoExtractor<L: Language, A: CostFunction<L>, B: CostFunction<L>> {
+    fronts: HashMap<Id, Front<L, A, B>>,
+    converged: bool,
+}
+
+// The default for how often each e-node may be re-evaluated, see `ParetoExtractor::new_with_limit`.
+pub const PARETO_MAX_EVALUATIONS: usize = 100;
+
+impl<L: Language, A: CostFunction<L>, B: CostFunction<L>> ParetoExtractor<L, A, B> {
+    pub fn new(eg: &EGraph<L>, a: &A, b: &B) -> Self {
+        Self::new_with_limit(eg, a, b, PARETO_MAX_EVALUATIONS)
+    }
+
+    // Every e-node is evaluated at most `max_evaluations` times, and each evaluation tries at most `max_evaluations` combinations of child entries.
+    // If that's not enough to reach the fixpoint (e.g. for non-monotone costs or large fronts), the fronts are incomplete and `converged()` returns false.
+    pub fn new_with_limit(eg: &EGraph<L>, a: &A, b: &B, max_evaluations: usize) -> Self {
+        if eg.check_level() >= CheckLevel::PerRebuild { eg.check(); }
+
+        let mut fronts: HashMap<Id, Front<L, A, B>> = HashMap::default();
+        let mut nodes = Vec::new();
+        for id in eg.ids() {
+            fronts.insert(id, Vec::new());
+            for x in eg.enodes(id) {
+                nodes.push((id, eg.class_nf(&x)));
+            }
+        }
+
+        // parents[c] are the indices of the e-nodes in `nodes` that have `c` as a child.
+        let mut parents: HashMap<Id, Vec<usize>> = HashMap::default();
+        for (k, (_, x)) in nodes.iter().enumerate() {
+            for c in x.ids() {
+                let v = parents.entry(c).or_default();
+                if !v.contains(&k) { v.push(k); }
+            }
+        }
+
+        // Only e-nodes whose children got a new front entry are evaluated again.
+        let mut dirty: IndexSet<usize> = (0..nodes.len()).rev().collect();
+        let mut evaluations = vec![0; nodes.len()];
+        let mut converged = true;
+
+        while let Some(k) = dirty.pop() {
+            if evaluations[k] >= max_evaluations {
+                converged = false;
+                continue;
+            }
+            evaluations[k] += 1;
+
+            let (id, x) = &nodes[k];
+            let children: Vec<Id> = x.ids();
+
+            // An e-class occuring multiple times as a child uses the same entry each time.
+            let mut distinct: Vec<Id> = Vec::new();
+            for c in &children {
+                if !distinct.contains(c) { distinct.push(*c); }
+            }
+
+            let mut options: Vec<Vec<usize>> = distinct.iter()
+                .map(|c| {
+                    let f = &fronts[c];
+                    let mut v = live_indices(f);
+                    v.sort_by(|x, y| (&f[*x].cost_a, &f[*x].cost_b).cmp(&(&f[*y].cost_a, &f[*y].cost_b)));
+                    v
+                })
+                .collect();
+
+            // The number of combinations is the product of the front sizes, so it's capped.
+            // Entries are dropped from the middle of the largest front, which keeps the best entry for each cost as long as possible.
+            while options.iter().fold(1usize, |acc, o| acc.saturating_mul(o.len())) > max_evaluations {
+                converged = false;
+                let o = options.iter_mut().max_by_key(|o| o.len()).unwrap();
+                if o.len() <= 1 { break; }
+                if o.len() > 2 { o.remove(o.len() / 2); } else { o.pop(); }
+            }
+
+            let mut candidates = Vec::new();
+            for combo in cartesian(&options) {
+                let pick: HashMap<Id, usize> = distinct.iter().copied().zip(combo).collect();
+                let cost_a = a.cost(x, |i| fronts[&i][pick[&i]].cost_a.clone());
+                let cost_b = b.cost(x, |i| fronts[&i][pick[&i]].cost_b.clone());
+                let choices = children.iter().map(|c| pick[c]).collect();
+                candidates.push(ParetoEntry {
+                    cost_a,
+                    cost_b,
+                    enode: x.clone(),
+                    choices,
+                    dominated: false,
+                });
+            }
+
+            let front = fronts.get_mut(id).unwrap();
+            let mut changed = false;
+            for cand in candidates {
+                changed |= insert_entry(front, cand);
+            }
+
+            if changed {
+                for p in parents.get(id).into_iter().flatten() {
+                    dirty.insert(*p);
+                }
+            }
+        }
+
+        Self { fronts, converged }
+    }
+
+    // Whether the fixpoint was reached, i.e. the fronts are complete.
+    pub fn converged(&self) -> bool {
+        self.converged
+    }
+
+    // Returns all non-dominated terms of `i`, sorted by their first cost.
+    pub fn front(&self, i: &AppliedId, eg: &EGraph<L>) -> Vec<(A::Cost, B::Cost, RecExpr<L>)> {
+        let i = eg.find_applied_id(i);
+
+        let mut out: Vec<_> = live_indices(&self.fronts[&i.id]).into_iter()
+            .map(|idx| {
+                let e = &self.fronts[&i.id][idx];
+                (e.cost_a.clone(), e.cost_b.clone(), eg.slot_allocator().scope(|| self.term(&i, idx, eg)))
+            })
+            .collect();
+        out.sort_by(|x, y| (&x.0, &x.1).cmp(&(&y.0, &y.1)));
+        out
+    }
+
+    fn term(&self, i: &AppliedId, idx: usize, eg: &EGraph<L>) -> RecExpr<L> {
+        let i = eg.find_applied_id(i);
+        let e = &self.fronts[&i.id][idx];
+
+        // redundant slots of the stored e-node are not covered by i.m, they get fresh names.
+        let l = e.enode.apply_slotmap_fresh(&i.m);
+        let children = l.applied_id_occurences()
+                        .into_iter()
+                        .zip(e.choices.iter())
+                        .map(|(child, j)| self.term(&child, *j, eg))
+                        .collect();
+
+        RecExpr {
+            node: l,
+            children,
+        }
+    }
+}
+
+pub fn pareto_extract<L: Language, A: CostFunction<L>, B: CostFunction<L>>(i: AppliedId, eg: &EGraph<L>, a: &A, b: &B) -> Vec<(A::Cost, B::Cost, RecExpr<L>)> {
+    ParetoExtractor::new(eg, a, b).front(&i, eg)
+}
+
+fn live_indices<L, CA, CB>(front: &[ParetoEntry<L, CA, CB>]) -> Vec<usize> {
+    (0..front.len()).filter(|i| !front[*i].dominated).collect()
+}
+
+// returns whether `cand` was added to the front.
+fn insert_entry<L, CA: Ord, CB: Ord>(front: &mut Vec<ParetoEntry<L, CA, CB>>, cand: ParetoEntry<L, CA, CB>) -> bool {
+    let dominates = |x: &ParetoEntry<L, CA, CB>, y: &ParetoEntry<L, CA, CB>| x.cost_a <= y.cost_a && x.cost_b <= y.cost_b;
+
+    // Entries with equal costs are considered dominated too, so that each point of the front is only found once.
+    if front.iter().any(|e| !e.dominated && dominates(e, &cand)) {
+        return false;
+    }
+
+    for e in front.iter_mut() {
+        if !e.dominated && dominates(&cand, e) {
+            e.dominated = true;
+        }
+    }
+    front.push(cand);
+    true
+}
+
+// all combinations of picking one element from each of the inner Vecs.
+fn cartesian(options: &[Vec<usize>]) -> Vec<Vec<usize>> {
+    let mut acc: Vec<Vec<usize>> = vec![Vec::new()];
+    for opt in options {
+        let mut next = Vec::new();
+        for a in &acc {
+            for x in opt {
+                let mut a = a.clone();
+                a.push(*x);
+                next.push(a);
+            }
+        }
+        acc = next;
+    }
+    acc
+}
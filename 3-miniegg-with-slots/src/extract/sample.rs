@@ -0,0 +1,90 @@
+use crate::*;
+
+use rand::prelude::*;
+use rand::distributions::WeightedIndex;
+
+// Draws random terms from e-classes, e.g. for differential testing.
+// E-graphs can represent infinitely many terms, hence sampled terms are limited to a maximal height.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SampleWeighting {
+    // every (viable) e-node of an e-class is equally likely.
+    Uniform,
+
+    // every term of an e-class (up to the height limit) is equally likely.
+    // Hence e-nodes with many subterms are chosen more often.
+    TermCount,
+}
+
+pub struct Sampler<'a, L: Language> {
+    eg: &'a EGraph<L>,
+    weighting: SampleWeighting,
+    max_height: usize,
+
+    // counts[d][i] is the number of terms of height <= d in the e-class i.
+    // f64, as these numbers explode quickly.
+    counts: Vec<HashMap<Id, f64>>,
+}
+
+impl<'a, L: Language> Sampler<'a, L> {
+    pub fn new(eg: &'a EGraph<L>, max_height: usize, weighting: SampleWeighting) -> Self {
+        let ids = eg.ids();
+        let mut counts: Vec<HashMap<Id, f64>> = vec![ids.iter().map(|i| (*i, 0.0)).collect()];
+        for d in 1..=max_height {
+            let mut m = HashMap::default();
+            for i in &ids {
+                let c: f64 = eg.enodes(*i).iter()
+                               .map(|x| x.ids().iter().map(|j| counts[d-1][&eg.find_id(*j)]).product::<f64>())
+                               .sum();
+                m.insert(*i, c);
+            }
+            counts.push(m);
+        }
+
+        Sampler { eg, weighting, max_height, counts }
+    }
+
+    // Returns None, if `i` has no term within the height limit.
+    // Every binder of the returned term is fresh.
+    pub fn sample(&self, i: &AppliedId, rng: &mut impl Rng) -> Option<RecExpr<L>> {
+        self.sample_impl(i, self.max_height, rng)
+    }
+
+    fn sample_impl(&self, i: &AppliedId, d: usize, rng: &mut impl Rng) -> Option<RecExpr<L>> {
+        if d == 0 { return None; }
+
+        // The order of a HashSet is not deterministic, so we sort by the slot-independent shape.
+        // This way, the same seed yields the same terms.
+        let mut candidates: Vec<L> = self.eg.enodes_applied(i).into_iter().collect();
+        candidates.sort_by_cached_key(shape_key);
+
+        let weights: Vec<f64> = candidates.iter().map(|x| {
+            let w: f64 = x.ids().iter().map(|j| self.counts[d-1][&self.eg.find_id(*j)]).product();
+            match self.weighting {
+                SampleWeighting::TermCount => w,
+                SampleWeighting::Uniform => if w > 0.0 { 1.0 } else { 0.0 },
+            }
+        }).collect();
+        let idx = WeightedIndex::new(&weights).ok()?.sample(rng);
+
+        let node = candidates[idx].refresh_private();
+        let children = node.applied_id_occurences().iter()
+                           .map(|c| self.sample_impl(c, d-1, rng).unwrap())
+                           .collect();
+        Some(RecExpr { node, children })
+    }
+}
+
+// A structural sorting key of the weak shape of `x`.
+fn shape_key<L: Language>(x: &L) -> (String, Vec<AppliedId>, Vec<Slot>) {
+    let sh = x.weak_shape().0;
+    (sh.to_op().0, sh.applied_id_occurences(), sh.all_slot_occurences())
+}
+
+// Draws `n` terms from `i`, each with height <= max_height.
+// Returns None, if `i` has no term within the height limit. Otherwise, there are exactly `n` terms.
+pub fn sample_terms<L: Language>(i: &AppliedId, eg: &EGraph<L>, n: usize, max_height: usize, seed: u64) -> Option<Vec<RecExpr<L>>> {
+    let sampler = Sampler::new(eg, max_height, SampleWeighting::TermCount);
+    let mut rng = StdRng::seed_from_u64(seed);
+    (0..n).map(|_| sampler.sample(i, &mut rng)).collect()
+}
@@ -0,0 +1,314 @@
+use crate::*;
+
+// Sketches are partial descriptions of terms, as in https://github.com/Bastacyclop/egg-sketches.
+// - `?` matches any term.
+// - `(contains s)` matches any term that has a subterm matching s.
+// - `(or s1 s2 ...)` matches if one of the s_i matches.
+// - every other node has to match an e-node, and its children have to match the child sketches.
+//
+// Slots in a sketch are names, just like in a RecExpr:
+// - binders (private slots) match the binders of e-nodes up to renaming.
+// - free slots refer to the slots of the AppliedId that the sketch is matched against.
+//   The sketch inside of a `contains` is matched against e-classes with arbitrary slot names, so it may not have free slots.
+//   Otherwise the `contains` doesn't match anything.
+pub type Sketch<L> = RecExpr<SketchNode<L>>;
+
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub enum SketchNode<L: Language> {
+    Any,
+    Node(L),
+    Contains(AppliedId),
+    Or(Vec<AppliedId>),
+}
+
+impl<L: Language> Language for SketchNode<L> {
+    fn all_slot_occurences_mut(&mut self) -> Vec<&mut Slot> {
+        match self {
+            SketchNode::Node(x) => x.all_slot_occurences_mut(),
+            _ => vec![],
+        }
+    }
+
+    fn public_slot_occurences_mut(&mut self) -> Vec<&mut Slot> {
+        match self {
+            SketchNode::Node(x) => x.public_slot_occurences_mut(),
+            _ => vec![],
+        }
+    }
+
+    fn applied_id_occurences_mut(&mut self) -> Vec<&mut AppliedId> {
+        match self {
+            SketchNode::Any => vec![],
+            SketchNode::Node(x) => x.applied_id_occurences_mut(),
+            SketchNode::Contains(x) => vec![x],
+            SketchNode::Or(xs) => xs.iter_mut().collect(),
+        }
+    }
+
+    fn to_op(&self) -> (String, Vec<Child>) {
+        match self {
+            SketchNode::Any => (String::from("?"), vec![]),
+            SketchNode::Node(l) => l.to_op(),
+            SketchNode::Contains(x) => (String::from("contains"), vec![Child::AppliedId(x.clone())]),
+            SketchNode::Or(xs) => (String::from("or"), xs.iter().cloned().map(Child::AppliedId).collect()),
+        }
+    }
+
+    fn from_op(op: &str, children: Vec<Child>) -> Option<Self> {
+        match (op, &*children) {
+            ("?", []) => Some(SketchNode::Any),
+            ("contains", [Child::AppliedId(x)]) => Some(SketchNode::Contains(x.clone())),
+            ("or", [_, ..]) => {
+                let mut xs = Vec::new();
+                for c in &children {
+                    let Child::AppliedId(x) = c else { return None };
+                    xs.push(x.clone());
+                }
+                Some(SketchNode::Or(xs))
+            },
+            _ => L::from_op(op, children).map(SketchNode::Node),
+        }
+    }
+}
+
+// Finds the cheapest term of an e-class that satisfies a sketch.
+pub struct SketchExtractor<'a, L: Language, CF: CostFunction<L>> {
+    eg: &'a EGraph<L>,
+
+    // used for `?`.
+    extractor: Extractor<L, CF>,
+}
+
+impl<'a, L: Language, CF: CostFunction<L>> SketchExtractor<'a, L, CF> {
+    pub fn new(eg: &'a EGraph<L>, cf: CF) -> Self {
+        let extractor = Extractor::new_with(eg, cf);
+        SketchExtractor { eg, extractor }
+    }
+
+    // `i` determines the names of the free slots in `sketch`.
+    pub fn extract(&self, sketch: &Sketch<L>, i: &AppliedId) -> Option<(CF::Cost, RecExpr<L>)> {
+        let i = self.eg.find_applied_id(i);
+        match &sketch.node {
+            SketchNode::Any => {
+                let re = self.extractor.extract(i.clone(), self.eg)?;
+                let c = self.extractor.cost(&i, self.eg);
+                Some((c, re))
+            },
+            SketchNode::Node(n) => self.extract_node(n, &sketch.children, &i),
+            SketchNode::Contains(_) => self.extract_contains(&sketch.children[0], &i),
+            SketchNode::Or(_) => {
+                sketch.children.iter()
+                    .filter_map(|s| self.extract(s, &i))
+                    .min_by(|x, y| x.0.cmp(&y.0))
+            },
+        }
+    }
+
+    fn extract_node(&self, n: &L, children: &[Sketch<L>], i: &AppliedId) -> Option<(CF::Cost, RecExpr<L>)> {
+        let mut best: Option<(CF::Cost, RecExpr<L>)> = None;
+        for nn in self.eg.enodes_applied(i) {
+            let Some(mut n2) = rename_binders(&nn, n) else { continue };
+
+            // The children are independent, so each one uses its cheapest variant.
+            let mut costs = Vec::new();
+            let mut res = Vec::new();
+            for (sub_id, sub_sketch) in n2.applied_id_occurences_mut().into_iter().zip(children.iter()) {
+                let Some((c, re, variant)) = self.extract_variants(sub_sketch, sub_id) else { break };
+                *sub_id = variant;
+                costs.push(c);
+                res.push(re);
+            }
+            if res.len() != children.len() { continue; }
+
+            let c = self.cost_with_children(&n2, &costs);
+            if best.as_ref().map(|(b, _)| &c < b).unwrap_or(true) {
+                best = Some((c, RecExpr { node: n2, children: res }));
+            }
+        }
+        best
+    }
+
+    // Extracts `s` from the cheapest of the variants of `a` given by the symmetries of its class.
+    // The variants only differ in which slots of the class get the names that `s` refers to.
+    // Hence if `s` refers to none of them, only `a` itself is visited, and otherwise each assignment of these names is only tried once.
+    fn extract_variants(&self, s: &Sketch<L>, a: &AppliedId) -> Option<(CF::Cost, RecExpr<L>, AppliedId)> {
+        let a = self.eg.find_applied_id(a);
+        let free = s.free_slots();
+        let relevant = a.m.values().iter().any(|x| free.contains(x));
+
+        let mut best: Option<(CF::Cost, RecExpr<L>, AppliedId)> = None;
+        let mut seen: HashSet<Vec<Option<Slot>>> = HashSet::default();
+        self.eg.visit_applied_id_variants(&a, |s, x| relevant || x == a.m[s], |v| {
+            let inv = v.m.inverse();
+            if !seen.insert(free.iter().map(|x| inv.get(*x)).collect()) { return; }
+
+            let Some((c, re)) = self.extract(s, &v) else { return };
+            if best.as_ref().map(|(b, _, _)| &c < b).unwrap_or(true) {
+                best = Some((c, re, v));
+            }
+        });
+        best
+    }
+
+    // The cheapest term of each reachable e-class that contains a match for `s` is computed by fixpoint iteration.
+    // The e-classes are considered with their identity slot names, so `s` can't refer to the slots of `i`.
+    fn extract_contains(&self, s: &Sketch<L>, i: &AppliedId) -> Option<(CF::Cost, RecExpr<L>)> {
+        if !s.free_slots().is_empty() { return None; }
+
+        let eg = self.eg;
+
+        let mut reachable: Vec<Id> = vec![i.id];
+        let mut seen: HashSet<Id> = singleton_set(i.id);
+        let mut idx = 0;
+        while idx < reachable.len() {
+            for x in eg.enodes(reachable[idx]) {
+                for c in x.ids() {
+                    if seen.insert(c) { reachable.push(c); }
+                }
+            }
+            idx += 1;
+        }
+
+        // best[c] describes how to find `s` in the e-class `c`.
+        let mut best: HashMap<Id, (CF::Cost, ContainsChoice<L>)> = HashMap::default();
+        for c in &reachable {
+            let c_id = eg.mk_sem_identity_applied_id(*c);
+            if let Some((cost, _)) = self.extract(s, &c_id) {
+                best.insert(*c, (cost, ContainsChoice::Here));
+            }
+        }
+
+        // parents[c] are the e-nodes (in class normal form) of the reachable e-classes that have `c` as a child.
+        let mut parents: HashMap<Id, Vec<(Id, L)>> = HashMap::default();
+        for c in &reachable {
+            for x in eg.enodes(*c) {
+                let x = eg.class_nf(&x);
+                let mut ids = x.ids();
+                ids.sort();
+                ids.dedup();
+                for j in ids {
+                    parents.entry(j).or_default().push((*c, x.clone()));
+                }
+            }
+        }
+
+        // Only the parents of e-classes with a better choice are evaluated again.
+        let mut queue = Worklist::default();
+        for c in &reachable {
+            if best.contains_key(c) { queue.push(*c); }
+        }
+
+        while let Some(child) = queue.pop() {
+            let child_cost = best[&child].0.clone();
+            for (c, x) in parents.get(&child).into_iter().flatten() {
+                let ids = x.ids();
+                for k in (0..ids.len()).filter(|k| ids[*k] == child) {
+                    // other children without any term make this e-node unusable.
+                    let costs: Option<Vec<CF::Cost>> = ids.iter().enumerate().map(|(j, j_id)| {
+                        if j == k { Some(child_cost.clone()) }
+                        else { self.extractor.map.get(j_id).map(|x| x.1.clone()) }
+                    }).collect();
+                    let Some(costs) = costs else { continue };
+                    let cost = self.cost_with_children(x, &costs);
+
+                    // With non-strict costs, the choices could otherwise form a cycle.
+                    if best.get(c).map(|(b, _)| &cost < b).unwrap_or(true) && !reaches(&best, child, *c, eg) {
+                        best.insert(*c, (cost, ContainsChoice::Below(x.clone(), k)));
+                        queue.push(*c);
+                    }
+                }
+            }
+        }
+
+        let (cost, _) = best.get(&i.id)?.clone();
+        Some((cost, self.build_contains(s, i, &best)?))
+    }
+
+    fn build_contains(&self, s: &Sketch<L>, i: &AppliedId, best: &HashMap<Id, (CF::Cost, ContainsChoice<L>)>) -> Option<RecExpr<L>> {
+        let i = self.eg.find_applied_id(i);
+        match &best[&i.id].1 {
+            ContainsChoice::Here => Some(self.extract(s, &i)?.1),
+            ContainsChoice::Below(x, k) => {
+                // x uses the identity slot names of i.id.
+                let node = x.refresh_private().apply_slotmap_fresh(&i.m);
+                let children = node.applied_id_occurences().into_iter().enumerate().map(|(j, child)| {
+                    if j == *k { self.build_contains(s, &child, best) }
+                    else { self.extractor.extract(child, self.eg) }
+                }).collect::<Option<_>>()?;
+                Some(RecExpr { node, children })
+            },
+        }
+    }
+
+    // computes the cost of `n`, given the costs of the terms chosen for its children (in order).
+    fn cost_with_children(&self, n: &L, costs: &[CF::Cost]) -> CF::Cost {
+        // different children might share the same Id, hence we temporarily number them by position.
+        let mut n = n.clone();
+        for (k, x) in n.applied_id_occurences_mut().into_iter().enumerate() {
+            x.id = Id(k);
+        }
+        self.extractor.cost_function().cost(&n, |i| costs[i.0].clone())
+    }
+}
+
+// checks whether following the choices in `best` from `i` leads to `target`.
+fn reaches<L: Language, C>(best: &HashMap<Id, (C, ContainsChoice<L>)>, i: Id, target: Id, eg: &EGraph<L>) -> bool {
+    let mut i = eg.find_id(i);
+    let mut visited = HashSet::default();
+    while visited.insert(i) {
+        if i == target { return true; }
+        match best.get(&i) {
+            Some((_, ContainsChoice::Below(x, k))) => i = eg.find_id(x.ids()[*k]),
+            _ => return false,
+        }
+    }
+    false
+}
+
+#[derive(Clone)]
+enum ContainsChoice<L> {
+    // the e-class itself matches the sketch.
+    Here,
+
+    // the k-th child of this e-node contains the match.
+    Below(L, usize),
+}
+
+// Renames the binders of `n2` to the binders of the sketch-node `n`.
+// Returns None, if n2 and n don't agree on operator and slots.
+fn rename_binders<L: Language>(n2: &L, n: &L) -> Option<L> {
+    let null_n = nullify_app_ids(n);
+    let null_n2 = nullify_app_ids(n2);
+    let l = null_n2.all_slot_occurences();
+    let r = null_n.all_slot_occurences();
+    if l.len() != r.len() { return None; }
+
+    let prv = n2.private_slots();
+    let public = as_set(n2.public_slot_occurences());
+    let mut map = SlotMap::new();
+    for (x, y) in l.into_iter().zip(r) {
+        if prv.contains(&x) {
+            if map.get(x).map(|z| z != y).unwrap_or(false) { return None; }
+            map.insert(x, y);
+        } else if x != y {
+            return None;
+        }
+    }
+    if !map.is_bijection() { return None; }
+    if map.values().iter().any(|y| public.contains(y)) { return None; }
+
+    let mut out = n2.clone();
+    for x in out.private_slot_occurences_mut() {
+        // binders that the sketch doesn't mention can keep their (fresh) names.
+        if let Some(y) = map.get(*x) {
+            *x = y;
+        }
+    }
+
+    if nullify_app_ids(&out) != null_n { return None; }
+    Some(out)
+}
+
+pub fn sketch_extract<L: Language, CF: CostFunction<L>>(sketch: &Sketch<L>, i: &AppliedId, eg: &EGraph<L>, cf: CF) -> Option<RecExpr<L>> {
+    SketchExtractor::new(eg, cf).extract(sketch, i).map(|(_, re)| re)
+}
@@ -0,0 +1,317 @@
+use crate::*;
+
+use rand::prelude::*;
+
+fn rise_class(terms: &[&str], eg: &mut EGraph<RiseENode>) -> AppliedId {
+    let ids: Vec<AppliedId> = terms.iter().map(|s| eg.add_expr(RecExpr::parse(s).unwrap())).collect();
+    for x in &ids[1..] {
+        eg.union(&ids[0], x);
+    }
+    ids[0].clone()
+}
+
+#[test]
+fn op_weights_extract() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["sym_x", "(app sym_y sym_z)"], eg);
+
+    assert_eq!(extract::<_, AstSize>(i.clone(), eg).to_string(), "sym_x");
+
+    let cf = OpWeights::default().with("sym_x", 10);
+    assert_eq!(extract_with(i, eg, cf).to_string(), "(app sym_y sym_z)");
+}
+
+#[test]
+fn forbid_extract() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["(let s0 sym_x (var s0))", "(app sym_y sym_z)"], eg);
+
+    let cf = Forbid::new(AstSize, &["let"]);
+    assert_eq!(extract_with(i.clone(), eg, cf).to_string(), "(app sym_y sym_z)");
+
+    // AstSizeNoLet is the same thing.
+    assert_eq!(extract::<_, AstSizeNoLet>(i, eg).to_string(), "(app sym_y sym_z)");
+}
+
+#[test]
+fn lexicographic_extract() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["(app sym_a sym_b)", "(app sym_c sym_d)", "sym_e"], eg);
+
+    // first avoid sym_e and sym_a, then minimize size.
+    let first = OpWeights::new(0).with("sym_e", 1).with("sym_a", 1);
+    let cf = Lexicographic(first, AstSize);
+    assert_eq!(extract_with(i, eg, cf).to_string(), "(app sym_c sym_d)");
+}
+
+#[test]
+fn weighted_sum_extract() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["sym_x", "(app sym_y sym_z)"], eg);
+
+    let expensive_x = OpWeights::default().with("sym_x", 10);
+
+    let cf = WeightedSum::new(AstSize, 1, expensive_x.clone(), 1);
+    assert_eq!(extract_with(i.clone(), eg, cf).to_string(), "(app sym_y sym_z)");
+
+    let cf = WeightedSum::new(AstSize, 10, expensive_x, 1);
+    assert_eq!(extract_with(i, eg, cf).to_string(), "sym_x");
+}
+
+#[test]
+fn pareto_front() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["sym_x", "(app sym_y sym_z)", "(app sym_y (app sym_y sym_z))"], eg);
+    let j = eg.add_expr(RecExpr::parse("(app sym_f (app sym_y sym_z))").unwrap());
+
+    let b = OpWeights::default().with("sym_x", 10);
+    let front = pareto_extract(i, eg, &AstSize, &b);
+    let front: Vec<_> = front.into_iter().map(|(x, y, re)| (x, y, re.to_string())).collect();
+    assert_eq!(front, vec![
+        (1, 10, String::from("sym_x")),
+        (3, 3, String::from("(app sym_y sym_z)")),
+    ]);
+
+    // the front of a parent combines the fronts of its children.
+    let front = pareto_extract(j, eg, &AstSize, &b);
+    assert_eq!(front.len(), 2);
+    assert_eq!(front[0].2.to_string(), "(app sym_f sym_x)");
+    assert_eq!(front[1].2.to_string(), "(app sym_f (app sym_y sym_z))");
+}
+
+#[test]
+fn pareto_non_monotone() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("sym_x").unwrap());
+    let f = eg.add_expr(RecExpr::parse("sym_f").unwrap());
+    let j = eg.add(RiseENode::App(f, i.clone()));
+    eg.union(&i, &j);
+
+    let ex = ParetoExtractor::new_with_limit(eg, &Shrinking, &AstSize, 5);
+    assert!(!ex.converged());
+    assert!(ex.front(&i, eg).len() > 1);
+
+    let ex = ParetoExtractor::new(eg, &AstSize, &AstSize);
+    assert!(ex.converged());
+}
+
+#[test]
+fn pareto_redundant_slots() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app (var s0) sym_w)").unwrap());
+    let j = eg.add_expr(RecExpr::parse("sym_z").unwrap());
+    eg.union(&i, &j);
+
+    // s0 became redundant, so it's not covered by the slot map of the e-class.
+    let b = OpWeights::default().with("sym_z", 10);
+    let front = pareto_extract(j, eg, &AstSize, &b);
+    assert_eq!(front.len(), 2);
+    assert_eq!(front[0].2.to_string(), "sym_z");
+    let (cost_a, cost_b, re) = &front[1];
+    assert_eq!((*cost_a, *cost_b), (3, 3));
+    assert!(re.to_string().starts_with("(app (var s"));
+}
+
+#[test]
+fn pareto_capped_combinations() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["sym_x", "(app sym_y sym_z)", "(app sym_y (app sym_y (app sym_y sym_z)))"], eg);
+    let j = eg.add(RiseENode::App(i.clone(), i.clone()));
+    let k = eg.add(RiseENode::App(j.clone(), i.clone()));
+
+    let b = OpWeights::default().with("sym_x", 20).with("sym_z", 5);
+    let ex = ParetoExtractor::new(eg, &AstSize, &b);
+    assert!(ex.converged());
+    let full = ex.front(&k, eg);
+
+    // with at most 2 combinations per evaluation, the front is incomplete, but contains the cheapest term in the first cost.
+    let ex = ParetoExtractor::new_with_limit(eg, &AstSize, &b, 2);
+    assert!(!ex.converged());
+    let capped = ex.front(&k, eg);
+    assert!(capped.len() < full.len());
+    assert_eq!(capped[0], full[0]);
+    for (x, y, _) in &capped {
+        assert!(full.iter().any(|(x2, y2, _)| x2 <= x && y2 <= y));
+    }
+}
+
+fn re_size<L: Language>(re: &RecExpr<L>) -> usize {
+    1 + re.children.iter().map(re_size).sum::<usize>()
+}
+
+#[test]
+fn incremental_extract() {
+    let rules = rise_rules(SubstMethod::SmallStep);
+
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app (lam s0 (app (var s0) (var s0))) (lam s1 (var s1)))").unwrap());
+
+    let mut ex = IncrementalExtractor::new(eg, AstSize);
+    for _ in 0..6 {
+        do_rewrites(eg, &rules);
+        ex.update(eg);
+
+        let l = ex.extract(i.clone(), eg).unwrap();
+        let r = extract::<_, AstSize>(i.clone(), eg);
+        assert_eq!(re_size(&l), re_size(&r));
+
+        // the extracted term is really in the e-class.
+        let j = lookup_rec_expr(&l, eg).unwrap();
+        assert!(eg.eq(&i, &j));
+    }
+
+    // the identity function.
+    let out = ex.extract(i, eg).unwrap();
+    assert_eq!(re_size(&out), 2);
+    assert!(matches!(out.node, RiseENode::Lam(..)));
+}
+
+fn sketch(s: &str) -> Sketch<RiseENode> {
+    RecExpr::parse(s).unwrap()
+}
+
+#[test]
+fn sketch_node_or() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["(app sym_f sym_a)", "(app sym_g (app sym_h sym_b))", "sym_c"], eg);
+
+    let ex = SketchExtractor::new(eg, AstSize);
+    assert_eq!(ex.extract(&sketch("?"), &i).unwrap().1.to_string(), "sym_c");
+    assert_eq!(ex.extract(&sketch("(app sym_g ?)"), &i).unwrap().1.to_string(), "(app sym_g (app sym_h sym_b))");
+    let (c, re) = ex.extract(&sketch("(or (app sym_g ?) (app ? sym_a))"), &i).unwrap();
+    assert_eq!((c, re.to_string()), (3, String::from("(app sym_f sym_a)")));
+    assert!(ex.extract(&sketch("(app sym_h ?)"), &i).is_none());
+}
+
+#[test]
+fn sketch_contains() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(app sym_h (app sym_f sym_a))").unwrap());
+    rise_class(&["sym_a", "(app sym_k sym_c)"], eg);
+
+    let out = sketch_extract(&sketch("(contains sym_c)"), &i, eg, AstSize).unwrap();
+    assert_eq!(out.to_string(), "(app sym_h (app sym_f (app sym_k sym_c)))");
+
+    let out = sketch_extract(&sketch("(app sym_h (contains sym_a))"), &i, eg, AstSize).unwrap();
+    assert_eq!(out.to_string(), "(app sym_h (app sym_f sym_a))");
+
+    assert!(sketch_extract(&sketch("(contains sym_d)"), &i, eg, AstSize).is_none());
+}
+
+#[test]
+fn sketch_contains_free_slots() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(lam s0 (app sym_f (var s0)))").unwrap());
+    let v = eg.add_expr(RecExpr::parse("(var s0)").unwrap());
+    let v = eg.mk_sem_identity_applied_id(v.id);
+    let x = v.m.values_vec()[0];
+
+    // `x` is a free slot of the `contains`, which isn't supported even if `x` happens to be the slot name used by the `var` e-class.
+    let mut sk = sketch("(contains (var s0))");
+    for y in sk.children[0].node.all_slot_occurences_mut() { *y = x; }
+    assert!(sketch_extract(&sk, &v, eg, AstSize).is_none());
+    assert!(sketch_extract(&sk, &i, eg, AstSize).is_none());
+    assert!(sketch_extract(&sketch("(contains sym_f)"), &i, eg, AstSize).is_some());
+}
+
+#[test]
+fn sketch_contains_cycle() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["sym_x", "(app sym_f sym_x)"], eg);
+
+    // `Shrinking` prefers wrapping the match in sym_f, which would point the class to itself.
+    let out = sketch_extract(&sketch("(contains sym_x)"), &i, eg, Shrinking).unwrap();
+    assert_eq!(out.to_string(), "sym_x");
+}
+
+#[test]
+fn sketch_binders() {
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = rise_class(&["(lam s0 (app sym_f (var s0)))", "sym_g"], eg);
+
+    let out = sketch_extract(&sketch("(lam s5 (app ? (var s5)))"), &i, eg, AstSize).unwrap();
+    assert_eq!(out.to_string(), "(lam s5 (app sym_f (var s5)))");
+
+    // the body doesn't use the binder as its function.
+    assert!(sketch_extract(&sketch("(lam s5 (app (var s5) ?))"), &i, eg, AstSize).is_none());
+
+    // free slots refer to the slots of the AppliedId.
+    let j = rise_class(&["(app (var s0) sym_x)", "(app (var s0) sym_y)"], eg);
+    assert!(sketch_extract(&sketch("(app (var s0) sym_y)"), &j, eg, AstSize).is_some());
+    assert!(sketch_extract(&sketch("(app (var s1) sym_y)"), &j, eg, AstSize).is_none());
+
+    let out = sketch_extract(&sketch("(contains (lam s3 (app sym_f (var s3))))"), &i, eg, AstSize).unwrap();
+    assert_eq!(out.to_string(), "(lam s3 (app sym_f (var s3)))");
+}
+
+// Redundant slots can show up as free variables in sampled terms.
+// Binding them to an arbitrary closed term doesn't change the normal form.
+fn close(mut re: RecExpr<ENode>) -> RecExpr<ENode> {
+    let id = RecExpr::<ENode>::parse2("(lam x0 x0)");
+    let dummy = AppliedId::new(Id(0), SlotMap::new());
+    for s in re.free_slots() {
+        let lam = RecExpr { node: ENode::Lam(s, dummy.clone()), children: vec![re] };
+        re = RecExpr { node: ENode::App(dummy.clone(), dummy.clone()), children: vec![lam, id.clone()] };
+    }
+    re
+}
+
+#[test]
+fn sample_normalizes() {
+    let s = "(app (lam x0 (app x0 x0)) (lam x1 (app (lam x2 x2) x1)))";
+    let eg = &mut EGraph::<ENode>::new();
+    let i = eg.add_expr(RecExpr::<ENode>::parse2(s));
+    for _ in 0..4 {
+        rewrite_small_step(eg);
+    }
+
+    let nf = run(s);
+    for weighting in [SampleWeighting::Uniform, SampleWeighting::TermCount] {
+        let sampler = Sampler::new(eg, 8, weighting);
+        let mut rng = StdRng::seed_from_u64(42);
+        for _ in 0..20 {
+            let re = sampler.sample(&i, &mut rng).unwrap();
+            assert_eq!(run(&close(re).to_string2()), nf);
+        }
+    }
+
+    // the same seed yields the same terms (to_string2 names slots by their first occurence).
+    let a: Vec<String> = sample_terms(&i, eg, 10, 8, 7).unwrap().iter().map(|re| re.to_string2()).collect();
+    let b: Vec<String> = sample_terms(&i, eg, 10, 8, 7).unwrap().iter().map(|re| re.to_string2()).collect();
+    assert_eq!(a.len(), 10);
+    assert_eq!(a, b);
+
+    // there are no terms of height 1.
+    assert!(sample_terms(&i, eg, 10, 1, 7).is_none());
+}
+
+#[test]
+fn let_free_extract() {
+    // the e-class only has let-forms.
+    let eg = &mut EGraph::<LetENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(let s0 (lam s1 (var s1)) (app (var s0) (lam s2 (app (var s0) (var s2)))))").unwrap());
+    let out = extract_let_free(i.clone(), eg);
+    assert_eq!(run(&out.to_string()), run("(app (lam x0 (app x0 (lam x2 (app x0 x2)))) (lam x1 x1))"));
+
+    // let-free terms are preferred over substitution.
+    let j = eg.add_expr(RecExpr::<LetENode>::parse2("(lam x0 x0)"));
+    eg.union(&i, &j);
+    assert_eq!(extract_let_free(i, eg).to_string(), "(lam x0 x0)");
+
+    let eg = &mut EGraph::<RiseENode>::new();
+    let i = eg.add_expr(RecExpr::parse("(let s0 sym_a (app (var s0) (let s1 (var s0) (var s1))))").unwrap());
+    assert_eq!(extract_let_free(i, eg).to_string(), "(app sym_a sym_a)");
+}
+
+#[test]
+fn let_elimination_avoids_capture() {
+    // the inner binder s0 would capture the free s0 in (var s0).
+    let re = RecExpr::<LetENode>::parse("(lam s0 (let s1 (var s0) (lam s0 (app (var s1) (var s0)))))").unwrap();
+    let out = eliminate_lets(&re);
+    assert_eq!(norm(&out.to_string()), norm("(lam x0 (lam x1 (app x0 x1)))"));
+
+    // shadowed occurences are not substituted.
+    let re = RecExpr::<LetENode>::parse("(lam s0 (let s1 (var s0) (app (var s1) (lam s1 (var s1)))))").unwrap();
+    let out = eliminate_lets(&re);
+    assert_eq!(norm(&out.to_string()), norm("(lam x0 (app x0 (lam x1 x1)))"));
+}
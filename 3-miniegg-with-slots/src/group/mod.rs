@@ -1,4 +1,5 @@
 use crate::*;
+use std::collections::hash_map::Entry;
 
 #[cfg(test)]
 mod tst;
@@ -7,12 +8,18 @@ mod tst;
 // https://en.wikipedia.org/wiki/Schreier%27s_lemma
 // I define "x y" = x.compose(y)
 
-// Trivial implementation of permutation Group.
+// Permutation Group, represented by a stabilizer chain.
+// The chain is built using the Schreier–Sims algorithm: new generators are sifted through the chain, and only their non-trivial residues extend it.
 #[derive(Clone, Debug)]
 pub struct Group<P: Permutation> {
     // all perms are bijections : omega -> omega.
     // omega = keys(identity) = values(identity).
     identity: P,
+
+    // the generators of this group.
+    // For the lower levels of the chain, these are the residues of sifted Schreier generators.
+    generators: HashSet<P>,
+
     next: Option<Box<Next<P>>>,
 }
 
@@ -25,43 +32,96 @@ struct Next<P: Permutation> {
     // ot[x] is a perm that maps stab to x.
     ot: HashMap<Slot, P>,
 
+    // the stabilizer of `stab`.
     g: Group<P>,
 }
 
 impl<P: Permutation> Group<P> {
     pub fn new(identity: &P, generators: HashSet<P>) -> Self {
-        let identity = identity.clone();
-        let next = find_lowest_nonstab(&generators)
-                    .map(|s| Box::new(Next::new(s, &identity, generators)));
-        Group { identity, next }
+        let base: Vec<Slot> = find_lowest_nonstab(&generators).into_iter().collect();
+        Self::with_base(identity, generators, &base)
+    }
+
+    // Builds the group such that its base starts with `base`.
+    // Base points that are not moved by the group remain as levels with a trivial orbit.
+    pub fn with_base(identity: &P, generators: HashSet<P>, base: &[Slot]) -> Self {
+        let mut g = Group { identity: identity.clone(), generators: HashSet::default(), next: None };
+        for b in base.iter().rev() {
+            let ot = singleton_map(*b, identity.clone());
+            g = Group { identity: identity.clone(), generators: HashSet::default(), next: Some(Box::new(Next { stab: *b, ot, g })) };
+        }
+
+        // sorted, so that the resulting chain only depends on the set of generators.
+        let mut generators: Vec<P> = generators.into_iter().collect();
+        generators.sort_by_key(|p| p.to_slotmap());
+        for p in generators {
+            g.extend(p);
+        }
+        g
     }
 
     pub fn identity(identity: &P) -> Self {
         Self::new(identity, HashSet::default())
     }
 
+    // The same group, with a stabilizer chain whose base starts with `base`.
+    pub fn change_base(&self, base: &[Slot]) -> Self {
+        Self::with_base(&self.identity, self.generators.clone(), base)
+    }
+
+    // The stabilized slots of the chain, in order.
+    pub fn base(&self) -> Vec<Slot> {
+        let mut out = Vec::new();
+        let mut g = self;
+        while let Some(n) = &g.next {
+            out.push(n.stab);
+            g = &n.g;
+        }
+        out
+    }
+
     pub fn orbit(&self, s: Slot) -> HashSet<Slot> {
-        build_ot(s, &self.identity, &self.generators())
+        build_ot(s, &self.identity, &self.generators)
             .keys()
             .cloned()
             .collect()
     }
 
-    fn generators_impl(&self) -> HashSet<P> {
-        match &self.next {
-            None => HashSet::default(),
-            Some(n) => &n.ot.values().cloned().collect::<HashSet<_>>() | &n.g.generators_impl(),
-        }
-    }
-
     pub fn generators(&self) -> HashSet<P> {
-        let mut out = self.generators_impl();
+        let mut out = self.generators.clone();
         out.remove(&self.identity);
         out
     }
 
-    // Should be very rarely called.
-    pub fn all_perms(&self) -> HashSet<P> {
+    // The levels of the stabilizer chain: the coset representatives of each stabilizer, ordered by the image of the base point.
+    // Every element of the group is "u_n ... u_2 u_1" for exactly one choice of representatives, where u_1 is from the first level.
+    // Each level also lists the slots whose image is determined once its representative is chosen, i.e. the slots fixed by its stabilizer, but not by the one above.
+    pub fn transversals(&self) -> Vec<(Vec<&P>, Vec<Slot>)> {
+        let mut out = Vec::new();
+        let mut determined: HashSet<Slot> = HashSet::default();
+        let mut g = self;
+        while let Some(n) = &g.next {
+            let mut reps: Vec<(&Slot, &P)> = n.ot.iter().collect();
+            reps.sort_by_key(|(x, _)| **x);
+
+            let mut fixed: Vec<Slot> = self.identity.iter().map(|(x, _)| x).filter(|x| !n.g.moves(*x) && !determined.contains(x)).collect();
+            fixed.sort();
+            determined.extend(fixed.iter().copied());
+
+            out.push((reps.into_iter().map(|(_, p)| p).collect(), fixed));
+            g = &n.g;
+        }
+        out
+    }
+
+    // whether a generator of this group moves `s`.
+    fn moves(&self, s: Slot) -> bool {
+        self.generators.iter().any(|p| p[s] != s)
+    }
+
+    // Enumerates the whole group, which is only feasible for small groups. Hence it's only used for testing.
+    #[cfg(test)]
+    pub(crate) fn all_perms(&self) -> HashSet<P> {
         match &self.next {
             None => [self.identity.clone()].into_iter().collect(),
             Some(n) => {
@@ -113,35 +173,101 @@ impl<P: Permutation> Group<P> {
         }
     }
 
-    pub fn add(&mut self, p: P) {
-        self.add_set([p].into_iter().collect());
+    // Returns whether the group grew, i.e. whether `p` wasn't contained yet.
+    pub fn add(&mut self, p: P) -> bool {
+        self.extend(p)
     }
 
-    pub fn add_set(&mut self, mut perms: HashSet<P>) {
-        // There might be ways to make this faster, by iterating through the stab chain and determining at which layer this perm actually has an effect.
-        // But it's polytime, so fast enough I guess.
+    pub fn add_set(&mut self, perms: HashSet<P>) {
+        let mut perms: Vec<P> = perms.into_iter().collect();
+        perms.sort_by_key(|p| p.to_slotmap());
+        for p in perms {
+            self.extend(p);
+        }
+    }
 
-        perms.retain(|x| !self.contains(&x.to_slotmap()));
+    // One step of Schreier–Sims: if `p` is not yet contained, it becomes a generator of this level,
+    // and the new Schreier generators are sifted into the stabilizer.
+    fn extend(&mut self, p: P) -> bool {
+        if self.contains(&p.to_slotmap()) { return false; }
+        self.generators.insert(p.clone());
+
+        let identity = &self.identity;
+        let n = self.next.get_or_insert_with(|| {
+            // the base is extended by a slot moved by `p`.
+            let stab = find_lowest_nonstab(&singleton_set(p.clone())).unwrap();
+            let ot = singleton_map(stab, identity.clone());
+            Box::new(Next { stab, ot, g: Group::identity(identity) })
+        });
+        let new_points = extend_ot(n.stab, &mut n.ot, &self.generators, &p);
+
+        // The representatives of the old orbit points didn't change,
+        // so only the Schreier generators involving a new orbit point or `p` are new.
+        let mut schreier = HashSet::default();
+        for (x, r) in &n.ot {
+            if new_points.contains(x) {
+                for s in &self.generators {
+                    schreier.insert(schreier_generator(n.stab, &n.ot, r, s));
+                }
+            } else {
+                schreier.insert(schreier_generator(n.stab, &n.ot, r, &p));
+            }
+        }
 
-        if !perms.is_empty() {
-            *self = Group::new(&self.identity, &self.generators() | &perms);
+        // Schreier generators that are already contained are filtered out by `extend`.
+        let mut schreier: Vec<P> = schreier.into_iter().collect();
+        schreier.sort_by_key(|p| p.to_slotmap());
+        for s in schreier {
+            n.g.extend(s);
         }
+        true
     }
 
+    // The order of the group, computed from the orbit sizes.
     pub fn count(&self) -> usize {
         match &self.next {
             None => 1,
             Some(n) => n.ot.len() * n.g.count(),
         }
     }
-}
 
-impl<P: Permutation> Next<P> {
-    fn new(stab: Slot, identity: &P, generators: HashSet<P>) -> Self {
-        let ot = build_ot(stab, identity, &generators);
-        let generators = schreiers_lemma(stab, &ot, generators);
-        let g = Group::new(identity, generators);
-        Next { stab, ot, g }
+    // For a slotmap `m` defined on omega, the variants `g m` for g in this group are the elements of the coset "G m".
+    // Returns the g, for which `g m` maps the base to the smallest slots (lexicographically).
+    // Hence `g m` is the same for all elements of the coset, and can be used as its canonical representative.
+    pub fn canonical_rep(&self, m: &SlotMap) -> P {
+        match &self.next {
+            None => self.identity.clone(),
+            Some(n) => {
+                let (_, u) = n.ot.iter().min_by_key(|(x, _)| m[**x]).unwrap();
+                let q = n.g.canonical_rep(&u.to_slotmap().compose(m));
+                q.compose(u)
+            },
+        }
+    }
+
+    // A uniformly distributed element of the group.
+    pub fn random_element(&self, rng: &mut impl rand::Rng) -> P {
+        match &self.next {
+            None => self.identity.clone(),
+            Some(n) => {
+                let u = n.ot.values().nth(rng.gen_range(0..n.ot.len())).unwrap();
+                n.g.random_element(rng).compose(u)
+            },
+        }
+    }
+
+    // An estimate of the heap memory of the stabilizer chain, given the heap memory of each permutation.
+    pub fn heap_size(&self, perm: &impl Fn(&P) -> usize) -> usize {
+        perm(&self.identity)
+            + hash_table_size::<P, ()>(self.generators.capacity())
+            + self.generators.iter().map(perm).sum::<usize>()
+            + match &self.next {
+                None => 0,
+                Some(n) => std::mem::size_of::<Next<P>>()
+                         + hash_table_size::<Slot, P>(n.ot.capacity())
+                         + n.ot.values().map(perm).sum::<usize>()
+                         + n.g.heap_size(perm),
+            }
     }
 }
 
@@ -169,17 +295,45 @@ fn build_ot<P: Permutation>(stab: Slot, identity: &P, generators: &HashSet<P>) -
     ot
 }
 
-// extends the set of generators using Schreiers Lemma.
-fn schreiers_lemma<P: Permutation>(stab: Slot, ot: &HashMap<Slot, P>, generators: HashSet<P>) -> HashSet<P> {
-    let mut out = HashSet::default();
-    for (_, r) in ot {
-        for s in &generators {
-            let rs = r.compose(s);
-            let rs2_inv = ot[&rs[stab]].inverse();
-            out.insert(rs.compose(&rs2_inv));
+// Extends the orbit tree `ot` after `p` was added to `generators`.
+// Only the images of the old orbit under `p` and their images under all generators can be new.
+// Returns the new orbit points.
+fn extend_ot<P: Permutation>(stab: Slot, ot: &mut HashMap<Slot, P>, generators: &HashSet<P>, p: &P) -> HashSet<Slot> {
+    let mut new_points = HashSet::default();
+    let mut old: Vec<Slot> = ot.keys().copied().collect();
+    old.sort();
+
+    let mut todo = Vec::new();
+    for x in old {
+        let new = ot[&x].compose(p);
+        let target = new[stab];
+        if let Entry::Vacant(e) = ot.entry(target) {
+            e.insert(new);
+            new_points.insert(target);
+            todo.push(target);
         }
     }
-    out
+
+    while let Some(x) = todo.pop() {
+        for g in generators {
+            let new = ot[&x].compose(g);
+            let target = new[stab];
+            if let Entry::Vacant(e) = ot.entry(target) {
+                e.insert(new);
+                new_points.insert(target);
+                todo.push(target);
+            }
+        }
+    }
+
+    new_points
+}
+
+// The Schreier generator of the orbit representative `r` and the generator `s`, see Schreiers Lemma.
+fn schreier_generator<P: Permutation>(stab: Slot, ot: &HashMap<Slot, P>, r: &P, s: &P) -> P {
+    let rs = r.compose(s);
+    let rs2_inv = ot[&rs[stab]].inverse();
+    rs.compose(&rs2_inv)
 }
 
 // finds the lowest Slot that's not stabilized in at least one of the generators.
@@ -198,6 +352,10 @@ fn find_lowest_nonstab<P: Permutation>(generators: &HashSet<P>) -> Option<Slot>
     min
 }
 
+fn singleton_map<K: Eq + Hash, V>(k: K, v: V) -> HashMap<K, V> {
+    [(k, v)].into_iter().collect()
+}
+
 pub fn singleton_set<T: Eq + Hash>(t: T) -> HashSet<T> {
     [t].into_iter().collect()
 }
@@ -21,6 +21,86 @@ fn group_test3() {
     check_group([perm1, perm2]);
 }
 
+#[test]
+fn group_count_large() {
+    // S_8 is generated by a shift and a flip, and has 8! elements.
+    let perm1 = shift(8);
+    let perm2 = flip(8, 0, 1);
+    let identity = SlotMap::identity(&perm1.values());
+    let g = Group::new(&identity, [perm1.clone(), perm2].into_iter().collect());
+    assert_eq!(g.count(), 40320);
+    assert!(g.contains(&flip(8, 3, 6)));
+
+    // the cyclic group of order 8.
+    let g = Group::new(&identity, singleton_set(perm1));
+    assert_eq!(g.count(), 8);
+    assert!(!g.contains(&flip(8, 3, 6)));
+}
+
+#[test]
+fn group_add_incrementally() {
+    // the second generator fixes the base point of the first one, the third one grows its orbit, and the last one is already contained.
+    let perms = [flip(5, 0, 1), flip(5, 3, 4), shift(5), flip(5, 1, 2)];
+    let identity = SlotMap::identity(&perms[0].values());
+    let mut g = Group::identity(&identity);
+    for (i, p) in perms.iter().enumerate() {
+        g.add(p.clone());
+        let all: HashSet<Perm> = perms[..=i].iter().cloned().collect();
+        let expected = enrich(all.clone());
+        assert_eq!(g.count(), expected.len());
+        assert_eq!(g.all_perms(), expected);
+        assert_eq!(g.count(), Group::new(&identity, all).count());
+    }
+    assert_eq!(g.count(), 120);
+}
+
+#[test]
+fn group_change_base() {
+    let perm1 = shift(4);
+    let perm2 = flip(4, 0, 2);
+    let identity = SlotMap::identity(&perm1.values());
+    let g = Group::new(&identity, [perm1, perm2].into_iter().collect());
+    let h = g.change_base(&[s(3), s(1)]);
+    assert_eq!(&h.base()[..2], &[s(3), s(1)]);
+    assert_eq!(g.count(), h.count());
+    assert_eq!(g.all_perms(), h.all_perms());
+}
+
+#[test]
+fn group_canonical_rep() {
+    let perm1 = shift(5);
+    let perm2 = flip(5, 1, 3);
+    let identity = SlotMap::identity(&perm1.values());
+    let g = Group::new(&identity, [perm1, perm2].into_iter().collect());
+
+    // every element of the coset "G m" yields the same representative.
+    let m = mk_perm(5, |i| (i*2)%5);
+    let rep = |m: &Perm| g.canonical_rep(m).compose(m);
+    let expected = rep(&m);
+    for p in g.all_perms() {
+        assert_eq!(rep(&p.compose(&m)), expected);
+    }
+}
+
+#[test]
+fn group_random_element() {
+    use rand::SeedableRng;
+
+    let perm1 = shift(6);
+    let perm2 = flip(6, 0, 3);
+    let identity = SlotMap::identity(&perm1.values());
+    let g = Group::new(&identity, [perm1, perm2].into_iter().collect());
+
+    let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+    let mut seen = HashSet::default();
+    for _ in 0..(20 * g.count()) {
+        let p = g.random_element(&mut rng);
+        assert!(g.contains(&p));
+        seen.insert(p);
+    }
+    assert_eq!(seen.len(), g.count());
+}
+
 // perms:
 
 fn shift(n: usize) -> Perm {
@@ -42,8 +122,10 @@ fn check_group(generators: impl IntoIterator<Item=Perm>) {
     let generators: HashSet<Perm> = generators.into_iter().collect();
     let omega: HashSet<_> = generators.iter().next().unwrap().values();
     let identity = SlotMap::identity(&omega);
-    let l = Group::new(&identity, generators.clone()).all_perms();
+    let g = Group::new(&identity, generators.clone());
+    let l = g.all_perms();
     let r = enrich(generators);
+    assert_eq!(g.count(), r.len());
     assert_eq!(l, r);
 }
 
@@ -11,9 +11,6 @@ pub use tst::*;
 mod rewrite;
 pub use rewrite::*;
 
-mod my_cost;
-pub use my_cost::*;
-
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum ArithENode {
     // lambda calculus:
@@ -144,6 +141,22 @@ impl Language for ArithENode {
 
 }
 
+impl LetLanguage for ArithENode {
+    fn let_slot(&self) -> Option<Slot> {
+        match self {
+            ArithENode::Let(x, _, _) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn var_slot(&self) -> Option<Slot> {
+        match self {
+            ArithENode::Var(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
 
 use std::fmt::*;
 
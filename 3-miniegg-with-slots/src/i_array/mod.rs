@@ -6,9 +6,6 @@ use crate::*;
 mod rewrite;
 pub use rewrite::*;
 
-mod my_cost;
-pub use my_cost::*;
-
 mod parse;
 pub use parse::*;
 
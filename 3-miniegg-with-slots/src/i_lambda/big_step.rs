@@ -10,6 +10,9 @@ pub struct Candidate {
 // applies rewrites (only beta-reduction) for all applicable situations.
 pub fn rewrite_big_step(eg: &mut EGraph<ENode>) {
     for cand in candidates(eg) {
+        // `subst` adds e-nodes as it goes, so the budget is only checked once per candidate.
+        if eg.budget_exhausted() { break; }
+
         let app_id = eg.lookup(&cand.app).unwrap();
 
         let ENode::App(l, t) = cand.app.clone() else { panic!() };
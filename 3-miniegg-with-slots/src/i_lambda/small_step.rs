@@ -24,35 +24,36 @@ pub fn rewrite_small_step(eg: &mut EGraph<ENode>) {
         let b = b.apply_slotmap(&l_m);
 
         for b_node in eg.enodes_applied(&b) {
-            let new = step(x_root, t.clone(), &b_node, eg);
+            let Some(new) = step(x_root, t.clone(), &b_node, eg) else { return };
             eg.union(&new, &app_id);
         }
     }
 }
 
 // everything here has L0 slot-names.
-fn step(x: Slot, t: AppliedId, b: &ENode, eg: &mut EGraph<ENode>) -> AppliedId {
+// returns None, if the budget of the e-graph is exhausted.
+fn step(x: Slot, t: AppliedId, b: &ENode, eg: &mut EGraph<ENode>) -> Option<AppliedId> {
     if !b.slots().contains(&x) {
-        return eg.lookup(b).unwrap();
+        return Some(eg.lookup(b).unwrap());
     }
 
     match b {
-        ENode::Var(_) => t,
+        ENode::Var(_) => Some(t),
         ENode::App(l, r) => {
             let mut pack = |lr: &AppliedId| {
-                let a1 = eg.add(ENode::Lam(x, lr.clone()));
-                let a2 = eg.add(ENode::App(a1, t.clone()));
-                a2
+                let a1 = eg.try_add(ENode::Lam(x, lr.clone()))?;
+                let a2 = eg.try_add(ENode::App(a1, t.clone()))?;
+                Some(a2)
             };
-            let l = pack(l);
-            let r = pack(r);
-            eg.add(ENode::App(l, r))
+            let l = pack(l)?;
+            let r = pack(r)?;
+            eg.try_add(ENode::App(l, r))
         },
         ENode::Lam(y, bb) => {
-            let a1 = eg.add(ENode::Lam(x, bb.clone()));
-            let a2 = eg.add(ENode::App(a1, t.clone()));
-            let a3 = eg.add(ENode::Lam(*y, a2));
-            a3
+            let a1 = eg.try_add(ENode::Lam(x, bb.clone()))?;
+            let a2 = eg.try_add(ENode::App(a1, t.clone()))?;
+            let a3 = eg.try_add(ENode::Lam(*y, a2))?;
+            Some(a3)
         },
     }
 }
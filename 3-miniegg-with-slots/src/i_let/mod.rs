@@ -91,6 +91,22 @@ impl Language for LetENode {
     }
 }
 
+impl LetLanguage for LetENode {
+    fn let_slot(&self) -> Option<Slot> {
+        match self {
+            LetENode::Let(x, _, _) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn var_slot(&self) -> Option<Slot> {
+        match self {
+            LetENode::Var(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
 
 use std::fmt::*;
 
@@ -1,12 +1,15 @@
 use crate::*;
 
-pub struct LetReal(EGraph<LetENode>);
+use std::cell::RefCell;
+
+// The extractor is kept across steps, so that each extraction only has to look at what changed.
+pub struct LetReal(EGraph<LetENode>, RefCell<Option<IncrementalExtractor<LetENode, AstSizeNoLet>>>);
 
 impl Realization for LetReal {
     type Id = AppliedId;
 
     fn new() -> Self {
-        LetReal(EGraph::new())
+        LetReal(EGraph::new(), RefCell::new(None))
     }
 
     fn add_ast(&mut self, ast: &Ast) -> Self::Id {
@@ -15,7 +18,10 @@ impl Realization for LetReal {
     }
 
     fn extract_ast(&self, id: Self::Id) -> Ast {
-        let out = extract::<LetENode, AstSizeNoLet>(id, &self.0);
+        let mut ex = self.1.borrow_mut();
+        let ex = ex.get_or_insert_with(|| IncrementalExtractor::new(&self.0, AstSizeNoLet::default()));
+        ex.update(&self.0);
+        let out = ex.extract(id, &self.0).unwrap();
         Ast::parse(&out.to_string())
     }
 
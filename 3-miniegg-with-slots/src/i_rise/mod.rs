@@ -11,9 +11,6 @@ pub use tst::*;
 mod rewrite;
 pub use rewrite::*;
 
-mod my_cost;
-pub use my_cost::*;
-
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub enum RiseENode {
     // lambda calculus:
@@ -118,6 +115,22 @@ impl Language for RiseENode {
 
 }
 
+impl LetLanguage for RiseENode {
+    fn let_slot(&self) -> Option<Slot> {
+        match self {
+            RiseENode::Let(x, _, _) => Some(*x),
+            _ => None,
+        }
+    }
+
+    fn var_slot(&self) -> Option<Slot> {
+        match self {
+            RiseENode::Var(x) => Some(*x),
+            _ => None,
+        }
+    }
+}
+
 
 use std::fmt::*;
 
@@ -127,8 +127,8 @@ fn let_const() -> Rewrite<RiseENode> {
         applier: Box::new(move |(), eg| {
             for subst in ematch_all(eg, &pat) {
                 if eg.enodes_applied(&subst["c"]).iter().any(|n| matches!(n, RiseENode::Symbol(_) | RiseENode::Number(_))) {
-                    let orig = pattern_subst(eg, &pat, &subst);
-                    eg.union_justified(&orig, &subst["c"], Some("let-const".to_string()));
+                    let c = Pattern::parse("?c").unwrap();
+                    if eg.union_instantiations(&pat, &c, &subst, Some("let-const".to_string())) == UnionStatus::BudgetExhausted { break; }
                 }
             }
         }),
@@ -233,18 +233,18 @@ fn beta_extr() -> Rewrite<RiseENode> {
 
             let mut out: Vec<(Subst, RecExpr<RiseENode>)> = Vec::new();
             for subst in ematch_all(eg, &a) {
-                let b = extractor.extract(subst["?b"].clone(), eg);
-                let t = extractor.extract(subst["?t"].clone(), eg);
+                // only after a cancellation, the classes might not be extracted.
+                let (Some(b), Some(t)) = (extractor.extract(subst["?b"].clone(), eg), extractor.extract(subst["?t"].clone(), eg)) else { break };
                 let res = re_subst(s, b, &t);
                 out.push((subst, res));
             }
             out
         }),
         applier: Box::new(move |substs, eg| {
-            for (subst, res) in substs {
-                let orig = pattern_subst(eg, &pat, &subst);
-                let res = eg.add_expr(res);
-                eg.union(&orig, &res);
+            for (mut subst, res) in substs {
+                let Some(res) = eg.try_add_expr(res) else { break };
+                subst.insert(String::from("?res"), res);
+                if eg.union_instantiations(&pat, &pvar("?res"), &subst, None) == UnionStatus::BudgetExhausted { break; }
             }
         }),
     };
@@ -267,15 +267,15 @@ fn beta_extr_direct() -> Rewrite<RiseENode> {
 
             let mut out: Vec<(Subst, RecExpr<RiseENode>)> = Vec::new();
             for subst in ematch_all(eg, &a) {
-                let b = extractor.extract(subst["?b"].clone(), eg);
-                let t = extractor.extract(subst["?t"].clone(), eg);
+                // only after a cancellation, the classes might not be extracted.
+                let (Some(b), Some(t)) = (extractor.extract(subst["?b"].clone(), eg), extractor.extract(subst["?t"].clone(), eg)) else { break };
                 let res = re_subst(s, b, &t);
                 out.push((subst, res));
             }
-            for (subst, res) in out {
-                let orig = pattern_subst(eg, &pat, &subst);
-                let res = eg.add_expr(res);
-                eg.union(&orig, &res);
+            for (mut subst, res) in out {
+                let Some(res) = eg.try_add_expr(res) else { break };
+                subst.insert(String::from("?res"), res);
+                if eg.union_instantiations(&pat, &pvar("?res"), &subst, None) == UnionStatus::BudgetExhausted { break; }
             }
         }),
     };
@@ -1,6 +1,5 @@
 #![allow(unused_imports)]
 
-use symbol_table::GlobalSymbol as Symbol;
 use lamcalc::*;
 
 use std::hash::Hash;
@@ -11,8 +10,11 @@ use std::ops::Deref;
 
 pub type HashMap<K, V> = fnv::FnvHashMap<K, V>;
 pub type HashSet<T> = fnv::FnvHashSet<T>;
+pub type IndexSet<T> = indexmap::IndexSet<T, fnv::FnvBuildHasher>;
+pub type IndexMap<K, V> = indexmap::IndexMap<K, V, fnv::FnvBuildHasher>;
 
 // Whether to enable invariant-checks.
+// For the e-graph itself, this only sets the default of its runtime CheckLevel.
 #[cfg(feature = "checks")]
 const CHECKS: bool = true;
 #[cfg(not(feature = "checks"))]
@@ -21,9 +23,15 @@ const CHECKS: bool = false;
 mod types;
 pub use types::*;
 
+mod symbol;
+pub use symbol::*;
+
 mod parse;
 pub use parse::*;
 
+mod canonical;
+pub use canonical::*;
+
 mod lang;
 pub use lang::*;
 
@@ -54,6 +62,9 @@ pub use i_symbol::*;
 mod slotmap;
 pub use slotmap::*;
 
+mod slot_allocator;
+pub use slot_allocator::*;
+
 mod debug;
 
 mod egraph;
@@ -66,4 +77,4 @@ mod pattern;
 pub use pattern::*;
 
 mod group;
-use group::*;
+pub use group::*;
@@ -14,6 +14,8 @@ struct State {
 pub fn ematch_all<L: Language>(eg: &EGraph<L>, pattern: &Pattern<L>) -> Vec<Subst> {
     let mut out = Vec::new();
     for i in eg.ids() {
+        // a cancelled search returns the matches of the classes visited so far.
+        if eg.is_cancelled() { break; }
         let i = eg.mk_sem_identity_applied_id(i);
         out.extend(
             ematch_impl(pattern, State::default(), i, eg)
@@ -37,43 +39,85 @@ fn ematch_impl<L: Language>(pattern: &Pattern<L>, st: State, i: AppliedId, eg: &
             vec![st]
         },
         ENodeOrPVar::ENode(n) => {
-            let mut out = Vec::new();
-            for nn in eg.enodes_applied(&i) {
-                'nodeloop: for n2 in eg.get_group_compatible_weak_variants(&nn) {
-                    if CHECKS {
-                        assert_eq!(&nullify_app_ids(n), n);
-                    }
+            // The slots of `n` itself don't depend on the symmetries of the children, so they are matched once per e-node.
+            // Children matched by a pattern variable aren't permuted at all, as all their variants are equal.
+            if CHECKS {
+                assert_eq!(&nullify_app_ids(n), n);
+            }
+            let (n_sh, _) = n.weak_shape();
 
-                    let clear_n2 = nullify_app_ids(&n2);
-                    // We can use weak_shape here, as the inputs are nullified
-                    // i.e. they only have id0() without slot args, so there are no permutations possible.
-                    let (n_sh, _) = n.weak_shape();
-                    let (clear_n2_sh, _) = clear_n2.weak_shape();
-                    if n_sh != clear_n2_sh { continue 'nodeloop; }
+            let mut out = Vec::new();
+            'nodeloop: for nn in eg.enodes_applied(&i) {
+                // a cancelled search returns the matches of the e-nodes visited so far.
+                if eg.is_cancelled() { break; }
 
-                    let mut st = st.clone();
+                let clear_nn = nullify_app_ids(&nn);
+                let (clear_nn_sh, _) = clear_nn.weak_shape();
+                if n_sh != clear_nn_sh { continue 'nodeloop; }
 
-                    for (x, y) in clear_n2.all_slot_occurences().into_iter().zip(n.all_slot_occurences().into_iter()) {
-                        if !try_insert_compatible_slotmap_bij(x, y, &mut st.partial_slotmap) { continue 'nodeloop; }
-                    }
+                let mut st = st.clone();
+                for (x, y) in clear_nn.all_slot_occurences().into_iter().zip(n.all_slot_occurences()) {
+                    if !try_insert_compatible_slotmap_bij(x, y, &mut st.partial_slotmap) { continue 'nodeloop; }
+                }
 
-                    let mut acc = vec![st];
-                    for (sub_id, sub_pat) in n2.applied_id_occurences().into_iter().zip(pattern.children.iter()) {
-                        let mut next = Vec::new();
+                let mut acc = vec![st];
+                for (sub_id, sub_pat) in nn.applied_id_occurences().into_iter().zip(pattern.children.iter()) {
+                    let mut next = Vec::new();
+                    if matches!(sub_pat.node, ENodeOrPVar::PVar(_)) || !eg.has_symmetries(&sub_id) {
                         for a in acc {
                             next.extend(ematch_impl(sub_pat, a, sub_id.clone(), eg));
                         }
-                        acc = next;
+                    } else {
+                        // The child is matched once, without the bindings made so far.
+                        // Each of its variants yields these matches with the slots of `sub_id` renamed accordingly,
+                        // and the variants are expanded level by level, so that a slot binding conflicting with `a` cuts off all variants sharing it.
+                        let sub_id = eg.find_applied_id(&sub_id);
+                        let sub_matches = ematch_impl(sub_pat, State::default(), sub_id.clone(), eg);
+                        'variants: for a in acc {
+                            for r in &sub_matches {
+                                // partial matches are dropped, as they aren't matches yet.
+                                if eg.is_cancelled() { next.clear(); break 'variants; }
+                                let accept = |s: Slot, x: Slot| match r.partial_slotmap.get(sub_id.m[s]) {
+                                    Some(y) => is_compatible_slotmap_bij(x, y, &a.partial_slotmap),
+                                    None => true,
+                                };
+                                eg.visit_applied_id_variants(&sub_id, accept, |v| {
+                                    // maps the slots of `sub_id` to the ones of the variant `v`.
+                                    let rename = sub_id.m.inverse().compose(&v.m);
+                                    next.extend(merge_state(&a, r, &rename, eg));
+                                });
+                            }
+                        }
                     }
-
-                    out.extend(acc);
+                    acc = next;
                 }
+
+                out.extend(acc);
             }
             out
         },
     }
 }
 
+// Adds the bindings of `r` to `st`, with the egraph slots of `r` renamed by `rename` (slots missing in `rename` are kept).
+fn merge_state<L: Language>(st: &State, r: &State, rename: &SlotMap, eg: &EGraph<L>) -> Option<State> {
+    let rename = |x: Slot| rename.get(x).unwrap_or(x);
+    let mut st = st.clone();
+    for (x, y) in r.partial_slotmap.iter() {
+        if !try_insert_compatible_slotmap_bij(rename(x), y, &mut st.partial_slotmap) { return None; }
+    }
+    for (v, j) in &r.partial_subst {
+        let m = j.m.iter().map(|(x, y)| (x, rename(y))).collect::<Vec<_>>();
+        let j = AppliedId::new(j.id, SlotMap::from_pairs(&m));
+        if let Some(k) = st.partial_subst.get(v) {
+            if !eg.eq(&j, k) { return None; }
+        } else {
+            st.partial_subst.insert(v.clone(), j);
+        }
+    }
+    Some(st)
+}
+
 pub fn nullify_app_ids<L: Language>(l: &L) -> L {
     let mut l = l.clone();
     for x in l.applied_id_occurences_mut() {
@@ -90,6 +134,14 @@ fn try_insert_compatible_slotmap_bij(k: Slot, v: Slot, map: &mut SlotMap) -> boo
     map.is_bijection()
 }
 
+// whether `try_insert_compatible_slotmap_bij` would succeed.
+fn is_compatible_slotmap_bij(k: Slot, v: Slot, map: &SlotMap) -> bool {
+    match map.get(k) {
+        Some(v_old) => v_old == v,
+        None => map.iter().all(|(_, y)| y != v),
+    }
+}
+
 fn final_subst(s: State) -> Subst {
     let State {
         partial_subst: mut subst,
@@ -111,3 +163,36 @@ fn final_subst(s: State) -> Subst {
 
     subst
 }
+
+#[test]
+fn ematch_symmetric_children() {
+    let p = |s| RecExpr::<RiseENode>::parse(s).unwrap();
+    let mut eg = EGraph::<RiseENode>::new();
+
+    // a fully symmetric class with three slots.
+    let a = eg.add_expr(p("(app (app (var s0) (var s1)) (var s2))"));
+    let b = eg.add_expr(p("(app (app (var s1) (var s0)) (var s2))"));
+    let c = eg.add_expr(p("(app (app (var s0) (var s2)) (var s1))"));
+    eg.union(&a, &b);
+    eg.union(&a, &c);
+    eg.add_expr(p("(app (app sym_f (app (app (var s0) (var s1)) (var s2))) (app (app (var s3) (var s4)) (var s5)))"));
+
+    // pattern variables don't expand the symmetries of their children.
+    let apps = eg.ids().into_iter().flat_map(|i| eg.enodes(i)).filter(|n| matches!(n, RiseENode::App(..))).count();
+    assert_eq!(ematch_all(&eg, &Pattern::parse("(app ?a ?b)").unwrap()).len(), apps);
+
+    // a child that is matched structurally is permuted, but only the variants agreeing with the binder above yield matches.
+    eg.add_expr(p("(lam s0 (app (app (var s0) (var s1)) (var s2)))"));
+    let pat = Pattern::parse("(lam s7 (app (app (var s7) ?b) ?c))").unwrap();
+    let matches = ematch_all(&eg, &pat);
+
+    // the bound variable stays in the first position, the free ones are swapped, and get fresh slots in the match.
+    let var = eg.add_expr(p("(var s0)")).id;
+    let var = eg.find_id(var);
+    assert_eq!(matches.len(), 2);
+    for m in &matches {
+        assert_eq!((eg.find_id(m["b"].id), eg.find_id(m["c"].id)), (var, var));
+        assert_ne!(m["b"].slots(), m["c"].slots());
+        assert!(!m["b"].slots().contains(&Slot::new(7)) && !m["c"].slots().contains(&Slot::new(7)));
+    }
+}
@@ -18,6 +18,26 @@ pub fn pattern_subst<L: Language>(eg: &mut EGraph<L>, pattern: &Pattern<L>, subs
     }
 }
 
+// Like `pattern_subst`, but returns None instead of adding a new e-node once the budget is exhausted, see `EGraph::try_add`.
+// The sub-terms added until then stay in the e-graph.
+pub fn try_pattern_subst<L: Language>(eg: &mut EGraph<L>, pattern: &Pattern<L>, subst: &Subst) -> Option<AppliedId> {
+    match &pattern.node {
+        ENodeOrPVar::ENode(n) => {
+            let mut n = n.clone();
+            let refs: Vec<&mut _> = n.applied_id_occurences_mut();
+            assert_eq!(pattern.children.len(), refs.len());
+            for (r, p) in refs.into_iter().zip(&pattern.children) {
+                *r = try_pattern_subst(eg, p, subst)?;
+            }
+            if eg.lookup(&n).is_none() && eg.budget_exhausted() { return None; }
+            Some(eg.add_syn(n))
+        },
+        ENodeOrPVar::PVar(v) => {
+            Some(subst[v].clone())
+        },
+    }
+}
+
 // TODO maybe move into EGraph API?
 pub fn lookup_rec_expr<L: Language>(re: &RecExpr<L>, eg: &EGraph<L>) -> Option<AppliedId> {
     let mut n = re.node.clone();
@@ -23,11 +23,31 @@ fn any_to_t<T: Any>(t: Box<dyn Any>) -> T {
     *t.downcast().unwrap()
 }
 
-pub fn do_rewrites<L: Language>(eg: &mut EGraph<L>, rewrites: &[Rewrite<L>]) {
-    let ts: Vec<Box<dyn Any>> = rewrites.iter().map(|rw| (*rw.searcher)(eg)).collect();
-    for (rw, t) in rewrites.iter().zip(ts.into_iter()) {
-        (*rw.applier)(t, eg);
-    }
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RewriteStatus {
+    Completed,
+
+    // Some rewrites were skipped or only partially applied, see `EGraph::budget_exhausted`.
+    BudgetExhausted,
+
+    // Like BudgetExhausted, but due to `EGraph::is_cancelled`.
+    Cancelled,
+}
+
+pub fn do_rewrites<L: Language>(eg: &mut EGraph<L>, rewrites: &[Rewrite<L>]) -> RewriteStatus {
+    eg.with_slot_allocator(|eg| {
+        let ts: Vec<Box<dyn Any>> = rewrites.iter().map(|rw| (*rw.searcher)(eg)).collect();
+        let status = |eg: &EGraph<L>| {
+            if eg.is_cancelled() { RewriteStatus::Cancelled }
+            else if eg.budget_exhausted() { RewriteStatus::BudgetExhausted }
+            else { RewriteStatus::Completed }
+        };
+        for (rw, t) in rewrites.iter().zip(ts.into_iter()) {
+            if status(eg) != RewriteStatus::Completed { break; }
+            (*rw.applier)(t, eg);
+        }
+        status(eg)
+    })
 }
 
 // Indirect rewrites.
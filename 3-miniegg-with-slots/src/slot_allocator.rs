@@ -0,0 +1,150 @@
+use crate::*;
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+use std::sync::atomic::{AtomicU32, Ordering};
+
+// Fresh slots of the namespace `ns` are s-(ns * NAMESPACE_SIZE + k) for k = 1, 2, 3, ...
+pub(crate) const NAMESPACE_SIZE: i64 = 1_000_000_000_000;
+
+// A source of fresh slots.
+// Slot::fresh() draws from the allocator of the innermost `scope`, or from the default allocator of the current thread.
+//
+// The default allocator of each thread has namespace 0, and every other allocator (in particular the one of each e-graph) gets an unused namespace of the process.
+// Hence slots of different allocators never collide.
+// The k-th slot of an allocator only depends on the calls made to it, which makes e-graphs reproducible up to their namespace.
+#[derive(Clone, Debug)]
+pub struct SlotAllocator(Rc<AllocatorState>);
+
+#[derive(Debug)]
+struct AllocatorState {
+    namespace: u32,
+    counter: Cell<i64>,
+}
+
+static NEXT_NAMESPACE: AtomicU32 = AtomicU32::new(1);
+
+thread_local! {
+    static CURRENT: RefCell<SlotAllocator> = RefCell::new(SlotAllocator::with_state(0, 0));
+}
+
+impl Default for SlotAllocator {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SlotAllocator {
+    pub fn new() -> Self {
+        let ns = NEXT_NAMESPACE.fetch_add(1, Ordering::Relaxed);
+        Self::with_namespace(ns)
+    }
+
+    // Allocators with the same namespace generate the same slots, so they shouldn't be used for the same terms.
+    pub fn with_namespace(namespace: u32) -> Self {
+        Self::with_state(namespace, 0)
+    }
+
+    // continues an allocator that has generated `counter` slots so far.
+    pub(crate) fn with_state(namespace: u32, counter: i64) -> Self {
+        assert!((namespace as i64) < i64::MAX / NAMESPACE_SIZE, "SlotAllocator: namespace {namespace} is too large");
+        if namespace > 0 {
+            NEXT_NAMESPACE.fetch_max(namespace + 1, Ordering::Relaxed);
+        }
+        SlotAllocator(Rc::new(AllocatorState { namespace, counter: Cell::new(counter) }))
+    }
+
+    pub fn namespace(&self) -> u32 {
+        self.0.namespace
+    }
+
+    // the number of slots generated so far.
+    pub fn counter(&self) -> i64 {
+        self.0.counter.get()
+    }
+
+    // continues with the slot after the first `counter` slots.
+    pub(crate) fn set_counter(&self, counter: i64) {
+        self.0.counter.set(counter);
+    }
+
+    pub fn fresh(&self) -> Slot {
+        let k = self.0.counter.get() + 1;
+        assert!(k < NAMESPACE_SIZE, "SlotAllocator: out of fresh slots");
+        self.0.counter.set(k);
+        self.nth(k)
+    }
+
+    // the k-th slot generated by this allocator (counting from 1).
+    pub(crate) fn nth(&self, k: i64) -> Slot {
+        Slot::new_unchecked(-(self.0.namespace as i64 * NAMESPACE_SIZE + k))
+    }
+
+    // the inverse of `nth`, if `s` belongs to the namespace of this allocator.
+    pub(crate) fn index_of(&self, s: Slot) -> Option<i64> {
+        let k = -s.raw() - self.0.namespace as i64 * NAMESPACE_SIZE;
+        (s.raw() < 0 && 0 < k && k < NAMESPACE_SIZE).then_some(k)
+    }
+
+    // Runs `f`, such that all calls to Slot::fresh() within `f` use this allocator.
+    pub fn scope<R>(&self, f: impl FnOnce() -> R) -> R {
+        struct Reset(Option<SlotAllocator>);
+        impl Drop for Reset {
+            fn drop(&mut self) {
+                let prev = self.0.take().unwrap();
+                CURRENT.with(|c| *c.borrow_mut() = prev);
+            }
+        }
+
+        let prev = CURRENT.with(|c| std::mem::replace(&mut *c.borrow_mut(), self.clone()));
+        let _reset = Reset(Some(prev));
+        f()
+    }
+
+    // draws from the allocator of the innermost scope.
+    pub(crate) fn current_fresh() -> Slot {
+        CURRENT.with(|c| c.borrow().fresh())
+    }
+}
+
+#[test]
+fn allocator_scopes() {
+    let a = SlotAllocator::new();
+    let b = SlotAllocator::new();
+    assert_ne!(a.namespace(), b.namespace());
+
+    let x = a.scope(|| Slot::fresh());
+    let y = b.scope(|| (Slot::fresh(), a.scope(|| Slot::fresh()), Slot::fresh()));
+    assert_ne!(x, y.1);
+    assert_eq!(a.counter(), 2);
+    assert_eq!(b.counter(), 2);
+    assert_ne!(y.0, y.2);
+    assert_ne!(Slot::fresh(), x);
+}
+
+#[test]
+fn egraph_allocators() {
+    let p = |s| RecExpr::<RiseENode>::parse(s).unwrap();
+    let run = || {
+        let mut eg = EGraph::<RiseENode>::new();
+        let a = eg.add_expr(p("(lam s0 (app (var s0) (var s1)))"));
+        let b = eg.add_expr(p("(lam s2 (app (var s2) (var s1)))"));
+        eg.union(&a, &b);
+        eg.rebuild();
+        eg
+    };
+
+    // two e-graphs on the same thread draw from different namespaces.
+    let eg1 = run();
+    let eg2 = run();
+    assert_ne!(eg1.slot_allocator().namespace(), eg2.slot_allocator().namespace());
+    assert_eq!(eg1.slot_allocator().counter(), eg2.slot_allocator().counter());
+    let slots = |eg: &EGraph<RiseENode>| eg.ids().into_iter().flat_map(|i| eg.slots(i)).filter(|s| *s < Slot::new(0)).collect::<HashSet<_>>();
+    assert!(!slots(&eg1).is_empty());
+    assert!(slots(&eg1).is_disjoint(&slots(&eg2)));
+
+    // they are the same up to their namespace, regardless of what happened before.
+    Slot::fresh();
+    SlotAllocator::new();
+    assert_eq!(run().save(false), eg1.save(false));
+}
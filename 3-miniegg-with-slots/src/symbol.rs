@@ -0,0 +1,110 @@
+use crate::*;
+
+use std::str::FromStr;
+use symbol_table::GlobalSymbol;
+
+// An interned string.
+// Unlike GlobalSymbol, hashing and ordering only depend on the string itself, and not on the order in which strings were interned.
+// Otherwise, the iteration order of every HashMap containing e-nodes (and hence the whole e-graph) would depend on it.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Symbol(GlobalSymbol);
+
+impl Symbol {
+    pub fn new(s: impl AsRef<str>) -> Self {
+        Symbol(GlobalSymbol::new(s))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        self.0.as_str()
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+        self.as_str().cmp(other.as_str())
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(s: &str) -> Self {
+        Symbol::new(s)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(s: String) -> Self {
+        Symbol::new(s)
+    }
+}
+
+impl FromStr for Symbol {
+    type Err = std::convert::Infallible;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Symbol::new(s))
+    }
+}
+
+impl Debug for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl std::fmt::Display for Symbol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Display::fmt(&self.0, f)
+    }
+}
+
+#[test]
+fn symbol_hash_is_content_based() {
+    use std::hash::Hasher;
+
+    let a = Symbol::from("symbol_hash_test_b");
+    let b = Symbol::from("symbol_hash_test_a");
+
+    // `a` was interned first, but it's hashed & ordered like its string.
+    let mut h1 = fnv::FnvHasher::default();
+    a.hash(&mut h1);
+    let mut h2 = fnv::FnvHasher::default();
+    "symbol_hash_test_b".hash(&mut h2);
+    assert_eq!(h1.finish(), h2.finish());
+    assert!(b < a);
+    assert_eq!(a, Symbol::new("symbol_hash_test_b"));
+}
+
+#[test]
+fn egraph_is_deterministic_across_runs() {
+    // every run happens on a new thread, after interning other symbols and drawing from other allocators.
+    let run = |seen: &'static str| std::thread::spawn(move || {
+        Symbol::new(seen);
+        SlotAllocator::new().fresh();
+        Slot::fresh();
+
+        let mut eg = EGraph::<RiseENode>::new();
+        eg.add_expr(RecExpr::parse("(app sym_det_a sym_det_b)").unwrap());
+
+        // beta-reduction draws fresh slots.
+        eg.add_expr(RecExpr::parse("(app (lam s0 (app (var s0) (var s0))) (lam s1 (var s1)))").unwrap());
+        let rules = rise_rules(SubstMethod::SmallStep);
+        for _ in 0..2 {
+            do_rewrites(&mut eg, &rules);
+        }
+        eg.save(false)
+    }).join().unwrap();
+
+    assert_eq!(run("sym_det_b"), run("sym_det_c"));
+}
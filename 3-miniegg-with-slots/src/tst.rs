@@ -51,3 +51,16 @@ fn test_var() {
     let id = ids[0];
     assert!(eg.slots(id).is_empty());
 }
+
+// A cost function that gets cheaper with every e-node, so cycles are cheaper than the terms they contain.
+#[cfg(test)]
+pub struct Shrinking;
+
+#[cfg(test)]
+impl CostFunction<RiseENode> for Shrinking {
+    type Cost = u64;
+
+    fn cost<C>(&self, enode: &RiseENode, costs: C) -> u64 where C: Fn(Id) -> u64 {
+        enode.ids().into_iter().map(costs).min().map(|c| c.saturating_sub(1)).unwrap_or(100)
+    }
+}
@@ -3,7 +3,7 @@ use crate::*;
 #[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Id(pub usize);
 
-#[derive(Clone, Copy, Hash, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 // For each eclass, its slots form an interval [0..n].
 // An ENode contains three kinds of slots:
 // - free / exposed
@@ -13,6 +13,14 @@ pub struct Id(pub usize);
 // A slot is "flexible" if it's free or lambda.
 pub struct Slot(i64);
 
+// Slots are hashed by their index within their SlotAllocator's namespace.
+// Hence hash-based iteration orders, and everything depending on them, are the same for e-graphs in different namespaces.
+impl Hash for Slot {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        (self.0 % NAMESPACE_SIZE).hash(state);
+    }
+}
+
 #[derive(Clone, Hash, PartialEq, Eq, PartialOrd, Ord)]
 pub struct AppliedId {
     pub id: Id,
@@ -25,7 +33,7 @@ pub struct AppliedId {
 
 // The AppliedIds in `node` are ignored (any typically set to AppliedId::null()). They are replaced by the children RecExpr.
 // A non-fancy version of RecExpr that uses the slots as "names".
-#[derive(Clone, PartialEq, Eq)]
+#[derive(Clone, PartialEq, Eq, Hash)]
 pub struct RecExpr<L: Language> {
     pub node: L,
     pub children: Vec<RecExpr<L>>,
@@ -80,26 +88,11 @@ impl AppliedId {
 }
 
 impl Slot {
-    // Generates a fresh slot.
-    // The only way to create an equivalent Slot is by copying this one.
-    // Hence we can rule out any form of naming collisions with this one.
-    // (In theory, another thread could also create the same Slot, but we don't do multithreading for now so it's fine)
+    // Generates a fresh slot, using the current SlotAllocator.
+    // Each allocator has its own namespace, so the only way to create an equivalent Slot is by copying this one,
+    // unless an allocator is explicitly created with the namespace of another one (see SlotAllocator::with_namespace).
     pub fn fresh() -> Self {
-        use std::cell::RefCell;
-
-        // We choose ThreadLocal here, so that tests (that run in parallel threads) don't interfere.
-        // There were situations, where different Slot-names did affect hashmap ordering, and with that actually changed the output of the algorithm.
-        // Using this, all tests should run deterministically.
-
-        thread_local! {
-            static CTR: RefCell<i64> = RefCell::new(-1);
-        }
-
-        let u = CTR.with_borrow(|v| *v);
-
-        CTR.with_borrow_mut(|v| *v -= 1);
-
-        Slot(u)
+        SlotAllocator::current_fresh()
     }
 
     // creates the slot `su`.
@@ -115,6 +108,11 @@ impl Slot {
     pub fn new_unchecked(i: i64) -> Slot {
         Slot(i)
     }
+
+    // the inverse of `new_unchecked`.
+    pub(crate) fn raw(&self) -> i64 {
+        self.0
+    }
 }
 
 